@@ -0,0 +1,113 @@
+use crate::utils::journal;
+use camino::Utf8PathBuf;
+use std::fmt;
+use std::io;
+
+// The ways planning or carrying out renames can fail. Internally we pass these
+// around inside anyhow errors, and turn them back into Errors at the edge of
+// the library. Anything we don't have a variant for comes back as Other.
+
+#[derive(Debug)]
+pub enum Error {
+    AlreadyInSequence(Utf8PathBuf),
+    DoesNotExist(Utf8PathBuf),
+    Exists(Utf8PathBuf),
+    Incomplete { dir: Utf8PathBuf, cause: String },
+    Interrupted(Utf8PathBuf),
+    InvalidInput(String),
+    Io(io::Error),
+    NotADirectory(Utf8PathBuf),
+    NotInSequence(Utf8PathBuf),
+    Other(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::AlreadyInSequence(file) => {
+                write!(f, "{} is already in sequence. Use 'file move'", file)
+            }
+            Error::DoesNotExist(file) => write!(f, "{} does not exist", file),
+            Error::Exists(file) => write!(f, "{} exists", file),
+            Error::Incomplete { dir, cause } => {
+                write!(f, "{}. Run 'fseq dir recover {}'", cause, dir)
+            }
+            Error::Interrupted(dir) => write!(
+                f,
+                "{} exists. Run 'fseq dir recover {}' first",
+                journal::journal_path(dir),
+                dir
+            ),
+            Error::InvalidInput(msg) => write!(f, "{}", msg),
+            Error::Io(e) => write!(f, "{}", e),
+            Error::NotADirectory(dir) => write!(f, "{} is not a directory", dir),
+            Error::NotInSequence(file) => {
+                write!(f, "{} is not in sequence. Use 'file insert'", file)
+            }
+            Error::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        let e = match e.downcast::<Error>() {
+            Ok(e) => return e,
+            Err(e) => e,
+        };
+
+        match e.downcast::<io::Error>() {
+            Ok(e) => Error::Io(e),
+            Err(e) => Error::Other(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_from_anyhow() {
+        let e: anyhow::Error = Error::Exists(Utf8PathBuf::from("/a/b")).into();
+        assert!(matches!(Error::from(e), Error::Exists(_)));
+
+        let e: anyhow::Error = io::Error::from(io::ErrorKind::NotFound).into();
+        assert!(matches!(Error::from(e), Error::Io(_)));
+
+        let e = Error::from(anyhow!("something else"));
+        assert!(matches!(e, Error::Other(_)));
+        assert_eq!("something else", e.to_string());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            "/a/b does not exist",
+            Error::DoesNotExist(Utf8PathBuf::from("/a/b")).to_string()
+        );
+
+        assert_eq!(
+            "/a/.fseq.journal exists. Run 'fseq dir recover /a' first",
+            Error::Interrupted(Utf8PathBuf::from("/a")).to_string()
+        );
+    }
+}
@@ -0,0 +1,12 @@
+// fseq keeps files in numbered sequences, named after their directory. The
+// library plans renames as typed Plans, which it can also carry out. The fseq
+// binary is a command-line front end to it.
+
+pub mod error;
+pub mod plan;
+pub(crate) mod subcommands;
+pub(crate) mod utils;
+
+pub use error::{Error, Result};
+pub use plan::{Naming, Plan, Rename, SplitBy};
+pub use subcommands::dir_status::{DirStatus, SequenceStatus};
@@ -1,10 +1,11 @@
-use crate::utils::types::Opts;
-use camino::Utf8PathBuf;
-use clap::{Args, Parser, Subcommand};
+mod tree;
 
-mod subcommands;
-#[macro_use]
-mod utils;
+use anyhow::anyhow;
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Args, Parser, Subcommand};
+use common::verbose;
+use fseq::plan::{self, COMPOUND_EXTENSIONS, Naming, Plan, SplitBy};
+use std::time::Duration;
 
 const THE_TAG: &str = "raw";
 
@@ -20,6 +21,9 @@ struct Cli {
     /// Be explicit about all operations
     #[clap(short, long)]
     verbose: bool,
+    /// Extensions with more than one part, which are kept whole when renaming
+    #[clap(long, value_delimiter = ',', default_value = COMPOUND_EXTENSIONS)]
+    compound_exts: Vec<String>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -33,8 +37,10 @@ enum Commands {
 }
 
 #[derive(Debug, Args)]
-#[command(args_conflicts_with_subcommands = true)]
 struct DirArgs {
+    /// Operate, in parallel, on every directory beneath the given ones
+    #[clap(short, long, global = true)]
+    recurse: bool,
     #[command(subcommand)]
     /// consolidate the sequencing in a directory
     command: Option<DirCommands>,
@@ -47,11 +53,52 @@ enum DirCommands {
         #[arg(required = true)]
         dirs: Vec<Utf8PathBuf>,
     },
+    /// Moves all files from one directory into another, renaming them to fit its sequence
+    Merge {
+        /// Renumber all files in order of modification time, rather than appending
+        #[clap(short = 'a', long)]
+        by_age: bool,
+        src: Utf8PathBuf,
+        dest: Utf8PathBuf,
+    },
     /// Renumbers files which match the naming scheme in order of modification time  
     NumByAge {
         #[arg(required = true)]
         dirs: Vec<Utf8PathBuf>,
     },
+    /// Completes a run which was interrupted part-way through renaming files
+    Recover {
+        /// Undo the interrupted run rather than completing it
+        #[clap(long)]
+        rollback: bool,
+        #[arg(required = true)]
+        dirs: Vec<Utf8PathBuf>,
+    },
+    /// Breaks a directory into several new ones, by count, time gap, or tag
+    #[command(group(clap::ArgGroup::new("split_by").required(true).args(["every", "gap", "by_tag"])))]
+    Split {
+        /// Start a new directory every this many files
+        #[clap(short, long)]
+        every: Option<usize>,
+        /// Start a new directory wherever files' mtimes are further apart than this, e.g. 6h
+        #[clap(short, long, value_parser = plan::parse_gap)]
+        gap: Option<Duration>,
+        /// Put tagged and untagged files in separate directories
+        #[clap(short = 'T', long)]
+        by_tag: bool,
+        /// How to name the new directories. {dir} is the original name, {n} counts from 1
+        #[clap(short, long, default_value = "{dir}.{n}")]
+        pattern: String,
+        dir: Utf8PathBuf,
+    },
+    /// Reports on the sequencing in directories, without changing anything
+    Status {
+        /// Print the report as JSON
+        #[clap(short, long)]
+        json: bool,
+        #[arg(required = true)]
+        dirs: Vec<Utf8PathBuf>,
+    },
 }
 
 #[derive(Debug, Args)]
@@ -69,6 +116,20 @@ enum FileCommands {
         #[arg(required = true)]
         files: Vec<Utf8PathBuf>,
     },
+    /// Puts a file into the sequence at the given number, moving up the files after it
+    Insert {
+        /// Sequence number the file should have
+        #[arg(short, long, value_parser = clap::value_parser!(i32).range(1..))]
+        at: i32,
+        file: Utf8PathBuf,
+    },
+    /// Moves a file to a new place in the sequence, shuffling along the files between
+    Move {
+        file: Utf8PathBuf,
+        /// Sequence number the file should have
+        #[arg(short, long, value_parser = clap::value_parser!(i32).range(1..))]
+        to: i32,
+    },
     /// Sets the filename tag if it is not set already
     #[command(alias = "set-tag")]
     Set {
@@ -83,22 +144,56 @@ enum FileCommands {
     },
 }
 
+struct Opts {
+    noop: bool,
+    recurse: bool,
+    naming: Naming,
+    verbose: bool,
+}
+
 fn main() {
     let cli = Cli::parse();
-
     let opts = Opts {
         verbose: cli.verbose,
         noop: cli.noop,
-        tag: cli.tag.clone(),
+        recurse: matches!(&cli.command, Commands::Dir(dir) if dir.recurse),
+        naming: Naming::new(&cli.tag).with_compound_exts(&cli.compound_exts),
     };
+    let naming = &opts.naming;
 
     let result = match cli.command {
         Commands::Dir(dir) => match dir.command {
             Some(dir_cmd) => match dir_cmd {
                 DirCommands::Consolidate { dirs } => {
-                    subcommands::dir_consolidate::run(&dirs, &opts)
+                    on_dirs(&dirs, &opts, |dir| plan::consolidate(dir, naming))
                 }
-                DirCommands::NumByAge { dirs } => subcommands::dir_num_by_age::run(&dirs, &opts),
+                DirCommands::Merge { by_age, src, dest } => {
+                    on_one_dir("merge", &opts, || plan::merge(&src, &dest, naming, by_age))
+                }
+                DirCommands::NumByAge { dirs } => {
+                    on_dirs(&dirs, &opts, |dir| plan::num_by_age(dir, naming))
+                }
+                DirCommands::Recover { rollback, dirs } => {
+                    on_dirs(&dirs, &opts, |dir| recover(dir, rollback, &opts))
+                }
+                DirCommands::Split {
+                    every,
+                    gap,
+                    by_tag,
+                    pattern,
+                    dir,
+                } => {
+                    let split_by = match (every, gap, by_tag) {
+                        (Some(count), _, _) => SplitBy::Count(count),
+                        (_, Some(gap), _) => SplitBy::Gap(gap),
+                        _ => SplitBy::Tag,
+                    };
+
+                    on_one_dir("split", &opts, || {
+                        plan::split(&dir, split_by, &pattern, naming)
+                    })
+                }
+                DirCommands::Status { json, dirs } => status(&dirs, json, &opts),
             },
             None => {
                 eprintln!("ERROR: the 'dir' command needs a subcommand.");
@@ -107,9 +202,21 @@ fn main() {
         },
         Commands::File(file) => match file.command {
             Some(file_cmd) => match file_cmd {
-                FileCommands::Flip { files } => subcommands::file_flip::run(&files, &opts),
-                FileCommands::Set { files } => subcommands::file_set::run(&files, &opts),
-                FileCommands::Unset { files } => subcommands::file_unset::run(&files, &opts),
+                FileCommands::Flip { files } => {
+                    on_files(&files, &opts, |file| plan::flip_tag(file, naming))
+                }
+                FileCommands::Insert { at, file } => {
+                    on_files(&[file], &opts, |file| plan::insert(file, at, naming))
+                }
+                FileCommands::Move { file, to } => {
+                    on_files(&[file], &opts, |file| plan::move_to(file, to, naming))
+                }
+                FileCommands::Set { files } => {
+                    on_files(&files, &opts, |file| plan::set_tag(file, naming))
+                }
+                FileCommands::Unset { files } => {
+                    on_files(&files, &opts, |file| plan::unset_tag(file, naming))
+                }
             },
             None => {
                 eprintln!("ERROR: the 'file' command needs a subcommand.");
@@ -123,3 +230,154 @@ fn main() {
         Err(_) => std::process::exit(1),
     }
 }
+
+// Prints what a plan does, and unless this is a noop run, carries it out.
+// Returns the number of renames.
+fn carry_out(plan: &Plan, opts: &Opts) -> fseq::Result<usize> {
+    plan.new_dirs
+        .iter()
+        .for_each(|dir| verbose!(opts, "mkdir {}", dir));
+    plan.renames
+        .iter()
+        .for_each(|r| verbose!(opts, "{} -> {}", r.from, r.to));
+
+    if opts.noop {
+        return Ok(plan.renames.len());
+    }
+
+    let ret = plan.execute()?;
+
+    for dir in plan.emptied_dirs.iter() {
+        if dir.exists() {
+            eprintln!("WARNING: {} is not empty, so was not removed", dir);
+        } else {
+            verbose!(opts, "removed {}", dir);
+        }
+    }
+
+    Ok(ret)
+}
+
+// Plans and carries out func for every directory in the list, or with
+// --recurse, for every directory beneath them, in parallel.
+fn on_dirs<F>(dirlist: &[Utf8PathBuf], opts: &Opts, func: F) -> anyhow::Result<()>
+where
+    F: Fn(&Utf8Path) -> fseq::Result<Plan> + Sync,
+{
+    let run = |dir: &Utf8Path| {
+        func(dir)
+            .and_then(|plan| carry_out(&plan, opts))
+            .inspect_err(|e| eprintln!("ERROR: {}", e))
+    };
+
+    let errs = if opts.recurse {
+        let dirs = tree::dirs_under(dirlist)?;
+        let results = tree::in_parallel(&dirs, run);
+
+        for (dir, result) in dirs.iter().zip(results.iter()) {
+            match result {
+                Ok(count) => println!("{}: {} renamed", dir, count),
+                Err(_) => println!("{}: failed", dir),
+            }
+        }
+
+        let errs = results.iter().filter(|r| r.is_err()).count();
+        println!("{} directories, {} errors", dirs.len(), errs);
+        errs
+    } else {
+        dirlist.iter().filter(|dir| run(dir).is_err()).count()
+    };
+
+    if errs > 0 {
+        Err(anyhow!("run err"))
+    } else {
+        Ok(())
+    }
+}
+
+// For the commands which work on a single directory, and so can't recurse.
+fn on_one_dir<F>(command: &str, opts: &Opts, func: F) -> anyhow::Result<()>
+where
+    F: Fn() -> fseq::Result<Plan>,
+{
+    if opts.recurse {
+        eprintln!("ERROR: {} cannot be used with --recurse", command);
+        return Err(anyhow!("invalid input"));
+    }
+
+    match func().and_then(|plan| carry_out(&plan, opts)) {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            Err(anyhow!("action error"))
+        }
+    }
+}
+
+fn on_files<F>(flist: &[Utf8PathBuf], opts: &Opts, func: F) -> anyhow::Result<()>
+where
+    F: Fn(&Utf8Path) -> fseq::Result<Plan>,
+{
+    let errs = flist
+        .iter()
+        .filter(|file| {
+            func(file)
+                .and_then(|plan| carry_out(&plan, opts))
+                .inspect_err(|e| eprintln!("ERROR on {}: {}", file, e))
+                .is_err()
+        })
+        .count();
+
+    if errs > 0 {
+        Err(anyhow!("invalid input"))
+    } else {
+        Ok(())
+    }
+}
+
+fn recover(dir: &Utf8Path, rollback: bool, opts: &Opts) -> fseq::Result<Plan> {
+    let ret = plan::recover(dir, rollback)?;
+
+    if ret.is_empty() {
+        verbose!(opts, "{}: nothing to recover", dir);
+    }
+
+    Ok(ret)
+}
+
+fn status(dirlist: &[Utf8PathBuf], json: bool, opts: &Opts) -> anyhow::Result<()> {
+    let mut errs = 0;
+    let mut reports: Vec<fseq::DirStatus> = Vec::new();
+
+    let dirs = if opts.recurse {
+        tree::dirs_under(dirlist)?
+    } else {
+        dirlist.to_owned()
+    };
+
+    let results = tree::in_parallel(&dirs, |dir| plan::status(dir, &opts.naming));
+
+    for (dir, result) in dirs.iter().zip(results) {
+        match result {
+            Ok(report) => reports.push(report),
+            Err(e) => {
+                eprintln!("ERROR on {}: {}", dir, e);
+                errs += 1;
+            }
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        reports
+            .iter()
+            .for_each(|r| print!("{}", r.report(opts.verbose)));
+    }
+
+    if errs > 0 {
+        Err(anyhow!("run err"))
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,361 @@
+use crate::error::{Error, Result};
+use crate::subcommands::dir_split;
+use crate::subcommands::dir_status::{self, DirStatus};
+use crate::subcommands::{dir_consolidate, dir_merge, dir_num_by_age};
+use crate::utils::common;
+use crate::utils::dir::{DirExt, FilesInDir};
+use crate::utils::journal::Journal;
+use crate::utils::sidecar;
+use crate::utils::types::RenameActions;
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Serialize;
+use std::fs;
+
+pub use crate::subcommands::dir_split::{SplitBy, parse_gap};
+pub use crate::utils::extension::{COMPOUND_EXTENSIONS, Naming};
+
+// The library's way in. Each planning function works out what a command would
+// do, without touching anything, and returns it as a Plan. Plans include the
+// renames of any sidecar files, and are checked, so every source exists and
+// no destination is taken. Plan::execute() carries a plan out, journalling it
+// so an interrupted run can be recovered.
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Rename {
+    pub from: Utf8PathBuf,
+    pub to: Utf8PathBuf,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Plan {
+    // The directory the command works on. Executing the plan journals it
+    // there, so that is where `fseq dir recover` looks.
+    pub dir: Utf8PathBuf,
+    // Directories to create before renaming anything.
+    pub new_dirs: Vec<Utf8PathBuf>,
+    // Renames, in the order they must happen.
+    pub renames: Vec<Rename>,
+    // Directories to remove afterwards, if they have been emptied.
+    pub emptied_dirs: Vec<Utf8PathBuf>,
+    // Whether this plan completes or undoes an interrupted run in dir.
+    // Executing it replaces that run's journal.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub recovers: bool,
+}
+
+impl Plan {
+    fn from_actions(dir: &Utf8Path, actions: RenameActions, naming: &Naming) -> Result<Plan> {
+        let actions = sidecar::with_sidecars(actions, naming);
+        common::check_actions(&actions)?;
+
+        Ok(Plan {
+            dir: dir.to_path_buf(),
+            renames: actions
+                .into_iter()
+                .map(|(from, to)| Rename { from, to })
+                .collect(),
+            ..Plan::default()
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.new_dirs.is_empty() && self.renames.is_empty() && !self.recovers
+    }
+
+    // Carries the plan out, returning the number of renames. If it fails
+    // part-way, the directory's journal is left for `fseq dir recover`, and the
+    // error is Error::Incomplete.
+    pub fn execute(&self) -> Result<usize> {
+        for dir in self.new_dirs.iter() {
+            fs::create_dir(dir)?;
+        }
+
+        let actions: RenameActions = self
+            .renames
+            .iter()
+            .map(|r| (r.from.clone(), r.to.clone()))
+            .collect();
+
+        let ret = if self.recovers {
+            common::resume_actions(&self.dir, actions)?
+        } else {
+            common::apply(&self.dir, actions)?
+        };
+
+        for dir in self.emptied_dirs.iter() {
+            let _ = fs::remove_dir(dir);
+        }
+
+        Ok(ret)
+    }
+}
+
+pub fn consolidate(dir: &Utf8Path, naming: &Naming) -> Result<Plan> {
+    let dir = canonical_dir(dir)?;
+    Plan::from_actions(&dir, dir_consolidate::actions(&dir, naming)?, naming)
+}
+
+pub fn num_by_age(dir: &Utf8Path, naming: &Naming) -> Result<Plan> {
+    let dir = canonical_dir(dir)?;
+    Plan::from_actions(&dir, dir_num_by_age::actions(&dir, naming)?, naming)
+}
+
+pub fn merge(src: &Utf8Path, dest: &Utf8Path, naming: &Naming, by_age: bool) -> Result<Plan> {
+    let (src, dest) = (canonical_dir(src)?, canonical_dir(dest)?);
+
+    if src == dest {
+        return Err(Error::InvalidInput(format!(
+            "cannot merge {} into itself",
+            src
+        )));
+    }
+
+    // Everything is renamed into dest, so that is where the run is journalled.
+    let mut ret = Plan::from_actions(
+        &dest,
+        dir_merge::actions(&src, &dest, naming, by_age)?,
+        naming,
+    )?;
+    ret.emptied_dirs.push(src);
+    Ok(ret)
+}
+
+pub fn split(dir: &Utf8Path, split_by: SplitBy, pattern: &str, naming: &Naming) -> Result<Plan> {
+    let dir = canonical_dir(dir)?;
+    let groups = dir_split::groups(&dir, split_by, pattern, naming)?;
+    let actions = dir_split::actions(&groups, naming);
+
+    // The new directories don't exist yet, so checking can only look at the
+    // sources. The run is journalled in the directory being split, not in any
+    // of the new ones.
+    let mut ret = Plan::from_actions(&dir, actions, naming)?;
+    ret.new_dirs = groups
+        .into_iter()
+        .map(|(new_dir, _files)| new_dir)
+        .collect();
+    ret.emptied_dirs.push(dir);
+    Ok(ret)
+}
+
+pub fn set_tag(file: &Utf8Path, naming: &Naming) -> Result<Plan> {
+    let (file, files) = file_and_dir(file, naming)?;
+    Plan::from_actions(&files.untagged.dirname, files.set_tag(file)?, naming)
+}
+
+pub fn unset_tag(file: &Utf8Path, naming: &Naming) -> Result<Plan> {
+    let (file, files) = file_and_dir(file, naming)?;
+    Plan::from_actions(&files.untagged.dirname, files.unset_tag(file)?, naming)
+}
+
+pub fn flip_tag(file: &Utf8Path, naming: &Naming) -> Result<Plan> {
+    let (file, files) = file_and_dir(file, naming)?;
+    Plan::from_actions(&files.untagged.dirname, files.flip_tag(file)?, naming)
+}
+
+pub fn insert(file: &Utf8Path, at: i32, naming: &Naming) -> Result<Plan> {
+    let (file, files) = file_and_dir(file, naming)?;
+    Plan::from_actions(&files.untagged.dirname, files.insert_at(file, at)?, naming)
+}
+
+pub fn move_to(file: &Utf8Path, to: i32, naming: &Naming) -> Result<Plan> {
+    let (file, files) = file_and_dir(file, naming)?;
+    Plan::from_actions(&files.untagged.dirname, files.move_to(file, to)?, naming)
+}
+
+pub fn status(dir: &Utf8Path, naming: &Naming) -> Result<DirStatus> {
+    Ok(dir_status::status(&canonical_dir(dir)?, naming)?)
+}
+
+// Finishes the interrupted run in dir, or with rollback, undoes it. The plan
+// is empty if there is nothing to recover.
+pub fn recover(dir: &Utf8Path, rollback: bool) -> Result<Plan> {
+    if !dir.is_dir() {
+        return Err(Error::NotADirectory(dir.to_path_buf()));
+    }
+
+    let journal = match Journal::load(dir)? {
+        Some(journal) => journal,
+        None => return Ok(Plan::default()),
+    };
+
+    let actions = if rollback {
+        journal.rollback()
+    } else {
+        journal.remaining()
+    };
+
+    Ok(Plan {
+        renames: actions
+            .into_iter()
+            .map(|(from, to)| Rename { from, to })
+            .collect(),
+        dir: dir.to_path_buf(),
+        recovers: true,
+        ..Plan::default()
+    })
+}
+
+fn canonical_dir(dir: &Utf8Path) -> Result<Utf8PathBuf> {
+    let ret = dir.canonicalize_utf8()?;
+
+    if ret.is_dir() {
+        Ok(ret)
+    } else {
+        Err(Error::NotADirectory(ret))
+    }
+}
+
+fn file_and_dir(file: &Utf8Path, naming: &Naming) -> Result<(Utf8PathBuf, FilesInDir)> {
+    let file = file.canonicalize_utf8()?;
+
+    match file.parent() {
+        Some(dir) => {
+            let files = dir.categorise_files(naming)?;
+            Ok((file, files))
+        }
+        None => Err(Error::InvalidInput(format!("invalid file {}", file))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_utils::{fixture, fixture_dir};
+
+    #[test]
+    fn test_consolidate() {
+        let plan = consolidate(&fixture("sidecar.dir"), &Naming::new("tag")).unwrap();
+
+        assert!(plan.new_dirs.is_empty());
+        assert!(plan.emptied_dirs.is_empty());
+
+        assert_eq!(
+            Rename {
+                from: fixture("sidecar.dir/sidecar.dir.0003.jpg"),
+                to: fixture("sidecar.dir/sidecar.dir.0002.jpg"),
+            },
+            plan.renames[0]
+        );
+
+        // The sidecar comes along.
+        assert_eq!(
+            Rename {
+                from: fixture("sidecar.dir/sidecar.dir.0003.jpg.xmp"),
+                to: fixture("sidecar.dir/sidecar.dir.0002.jpg.xmp"),
+            },
+            plan.renames[1]
+        );
+
+        assert!(
+            consolidate(&fixture("sorted.dir"), &Naming::new("xx"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            consolidate(&fixture("no.such.dir"), &Naming::new("tag")),
+            Err(Error::Io(_))
+        ));
+
+        assert!(matches!(
+            consolidate(&fixture("some.dir/some.dir.0001.jpg"), &Naming::new("tag")),
+            Err(Error::NotADirectory(_))
+        ));
+
+        assert!(matches!(
+            insert(
+                &fixture("some.dir/some.dir.0001.jpg"),
+                2,
+                &Naming::new("tag")
+            ),
+            Err(Error::AlreadyInSequence(_))
+        ));
+
+        assert!(matches!(
+            move_to(&fixture("some.dir/random_name.jpg"), 2, &Naming::new("tag")),
+            Err(Error::NotInSequence(_))
+        ));
+
+        assert!(matches!(
+            merge(
+                &fixture("some.dir"),
+                &fixture("some.dir"),
+                &Naming::new("tag"),
+                false
+            ),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_split_and_execute() {
+        let (_tmp, dir) = fixture_dir(
+            "x.dir",
+            vec!["x.dir.0001.jpg", "x.dir.0002.jpg", "x.dir.0003.jpg"],
+        );
+
+        let plan = split(&dir, SplitBy::Count(2), "{dir}.{n}", &Naming::new("tag")).unwrap();
+
+        assert_eq!(
+            vec![dir.with_file_name("x.dir.1"), dir.with_file_name("x.dir.2")],
+            plan.new_dirs
+        );
+
+        assert_eq!(vec![dir.clone()], plan.emptied_dirs);
+        assert_eq!(3, plan.renames.len());
+
+        assert_eq!(3, plan.execute().unwrap());
+        assert!(!dir.exists());
+        assert!(dir.with_file_name("x.dir.2/x.dir.2.0001.jpg").exists());
+    }
+
+    #[test]
+    fn test_recover_split() {
+        let (_tmp, dir) = fixture_dir(
+            "y.dir",
+            vec!["y.dir.0001.jpg", "y.dir.0002.jpg", "y.dir.0003.jpg"],
+        );
+
+        let mut plan = split(&dir, SplitBy::Count(2), "{dir}.{n}", &Naming::new("tag")).unwrap();
+        assert_eq!(dir, plan.dir);
+
+        // Make the last rename fail, interrupting the run.
+        plan.renames[2].to = dir.with_file_name("no.such.dir/y.jpg");
+        assert!(matches!(plan.execute(), Err(Error::Incomplete { .. })));
+
+        // The journal is in the directory being split, so recovering that
+        // puts everything back.
+        let plan = recover(&dir, true).unwrap();
+        assert_eq!(2, plan.renames.len());
+        assert_eq!(2, plan.execute().unwrap());
+        assert!(dir.join("y.dir.0001.jpg").exists());
+        assert!(dir.join("y.dir.0002.jpg").exists());
+        assert!(recover(&dir, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recover() {
+        let (_tmp, dir) = fixture_dir("r.dir", vec!["r.dir.0002.jpg"]);
+
+        assert!(recover(&dir, false).unwrap().is_empty());
+        assert!(matches!(
+            recover(&dir.join("no.such.dir"), false),
+            Err(Error::NotADirectory(_))
+        ));
+
+        let actions = vec![(dir.join("r.dir.0002.jpg"), dir.join("r.dir.0001.jpg"))];
+        Journal::create(&dir, &actions).unwrap();
+
+        let plan = recover(&dir, false).unwrap();
+        assert_eq!(dir, plan.dir);
+        assert!(plan.recovers);
+        assert_eq!(1, plan.renames.len());
+
+        assert_eq!(1, plan.execute().unwrap());
+        assert!(dir.join("r.dir.0001.jpg").exists());
+        assert!(recover(&dir, false).unwrap().is_empty());
+    }
+}
@@ -1,20 +1,16 @@
-use crate::utils::common;
 use crate::utils::dir::{DirExt, FilesInDirSubtype};
+use crate::utils::extension::Naming;
 use crate::utils::file::PathExt;
-use crate::utils::types::{Opts, RenameActions, RenameActionsResult};
-use camino::{Utf8Path, Utf8PathBuf};
+use crate::utils::types::{RenameActions, RenameActionsResult};
+use camino::Utf8Path;
 
 // Consolidates a directory. If the filename numbers are non-contiguous, pull
 // down the highest numbers, renaming files until all holes are filled. Tagging
 // is preserved, and filenames not matching the base pattern ("rogues") are
 // renamed to fit the pattern. File extension is preserved.
-//
-pub fn run(dirlist: &Vec<Utf8PathBuf>, opts: &Opts) -> anyhow::Result<()> {
-    crate::run!(dirlist, opts)
-}
 
-fn actions(dir: &Utf8Path, tag: &str) -> RenameActionsResult {
-    let files = dir.categorise_files(tag.to_owned())?;
+pub(crate) fn actions(dir: &Utf8Path, naming: &Naming) -> RenameActionsResult {
+    let files = dir.categorise_files(naming)?;
 
     let mut actions = consolidate_actions_for_base(files.untagged);
     let tagged_actions = consolidate_actions_for_base(files.tagged);
@@ -33,7 +29,7 @@ fn consolidate_actions_for_base(files: FilesInDirSubtype) -> RenameActions {
     for (i, hole) in hole_list.iter().enumerate().take(min_len) {
         let index = numbered_len - 1 - i;
 
-        if let Some(file_num) = files.numbered_files[index].get_number()
+        if let Some(file_num) = files.numbered_files[index].get_number(&files.naming)
             && file_num > *hole
         {
             let source = &files.numbered_files[index];
@@ -60,7 +56,7 @@ mod test {
 
     #[test]
     fn test_consolidate_actions() {
-        let needs_work = actions(&fixture("some.dir"), "tag");
+        let needs_work = actions(&fixture("some.dir"), &Naming::new("tag"));
 
         let expected: RenameActions = vec![
             (
@@ -90,7 +86,7 @@ mod test {
         let expected_empty: RenameActions = Vec::new();
         assert_eq!(
             expected_empty,
-            actions(&fixture("sorted.dir"), "xx").unwrap()
+            actions(&fixture("sorted.dir"), &Naming::new("xx")).unwrap()
         );
     }
 }
@@ -0,0 +1,160 @@
+use crate::utils::dir::{DirExt, FilesInDirSubtype};
+use crate::utils::extension::Naming;
+use crate::utils::move_list::make_move_list;
+use crate::utils::types::{RenameActions, RenameActionsResult};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::fs;
+use std::time::SystemTime;
+
+// Moves every file from one managed directory into another, renaming them to
+// fit the destination's sequence. Tagging is preserved. By default, the
+// incoming files are numbered after the destination's highest number, in their
+// existing order. by_age instead renumbers everything in order of modification
+// time. The source directory is removed if it ends up empty.
+pub(crate) fn actions(
+    src: &Utf8Path,
+    dest: &Utf8Path,
+    naming: &Naming,
+    by_age: bool,
+) -> RenameActionsResult {
+    let incoming = src.categorise_files(naming)?;
+    let existing = dest.categorise_files(naming)?;
+
+    let (mut ret, tagged) = if by_age {
+        (
+            interleave_actions(&incoming.untagged, &existing.untagged)?,
+            interleave_actions(&incoming.tagged, &existing.tagged)?,
+        )
+    } else {
+        (
+            append_actions(&incoming.untagged, &existing.untagged),
+            append_actions(&incoming.tagged, &existing.tagged),
+        )
+    };
+
+    ret.extend(tagged);
+    Ok(ret)
+}
+
+fn incoming_files(from: &FilesInDirSubtype) -> impl Iterator<Item = &Utf8PathBuf> {
+    from.numbered_files.iter().chain(from.rogue_files.iter())
+}
+
+// Numbers the incoming files after the highest existing number. Numbered
+// files keep their order, and rogues follow them.
+fn append_actions(from: &FilesInDirSubtype, to: &FilesInDirSubtype) -> RenameActions {
+    let first = to.numbers.last().unwrap_or(&0) + 1;
+
+    incoming_files(from)
+        .enumerate()
+        .map(|(i, file)| (file.clone(), to.fname_from_stem(file, first + i as i32)))
+        .collect()
+}
+
+// Numbers the incoming files and the existing numbered files together, in
+// order of modification time.
+fn interleave_actions(from: &FilesInDirSubtype, to: &FilesInDirSubtype) -> RenameActionsResult {
+    let mut by_age: Vec<(SystemTime, &Utf8PathBuf)> = Vec::new();
+
+    for file in to.numbered_files.iter().chain(incoming_files(from)) {
+        by_age.push((fs::metadata(file)?.modified()?, file));
+    }
+
+    by_age.sort();
+
+    let moves = by_age
+        .iter()
+        .enumerate()
+        .map(|(i, (_mtime, file))| ((*file).clone(), to.fname_from_stem(file, i as i32 + 1)))
+        .filter(|(file, target)| file != target)
+        .collect();
+
+    make_move_list(moves, &to.naming)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_utils::fixture;
+
+    #[test]
+    fn test_merge_actions() {
+        let result = actions(
+            &fixture("age.dir"),
+            &fixture("some.dir"),
+            &Naming::new("tag"),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                (
+                    fixture("age.dir/age.dir.0001.jpg"),
+                    fixture("some.dir/some.dir.0006.jpg")
+                ),
+                (
+                    fixture("age.dir/age.dir.0002.jpg"),
+                    fixture("some.dir/some.dir.0007.jpg")
+                ),
+                (
+                    fixture("age.dir/age.dir.0003.jpg"),
+                    fixture("some.dir/some.dir.0008.jpg")
+                ),
+                (
+                    fixture("age.dir/age.dir.0004.jpg"),
+                    fixture("some.dir/some.dir.0009.jpg")
+                ),
+            ],
+            result
+        );
+
+        let result = actions(
+            &fixture("some.dir"),
+            &fixture("age.dir"),
+            &Naming::new("tag"),
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(11, result.len());
+
+        assert!(result.contains(&(
+            fixture("some.dir/random_name.jpg"),
+            fixture("age.dir/age.dir.0010.jpg")
+        )));
+
+        assert!(result.contains(&(
+            fixture("some.dir/some.dir.tag.1234.jpg"),
+            fixture("age.dir/age.dir.tag.0004.jpg")
+        )));
+
+        assert!(result.contains(&(
+            fixture("some.dir/random_name.tag.1234.jpg"),
+            fixture("age.dir/age.dir.tag.0005.jpg")
+        )));
+    }
+
+    #[test]
+    fn test_append_actions() {
+        let from = fixture("sorted.dir")
+            .categorise_files(&Naming::new("xx"))
+            .unwrap();
+
+        let to = fixture("some.dir")
+            .categorise_files(&Naming::new("xx"))
+            .unwrap();
+
+        let result = append_actions(&from.tagged, &to.tagged);
+
+        assert_eq!(5, result.len());
+
+        assert_eq!(
+            (
+                fixture("sorted.dir/sorted.dir.xx.0001.jpg"),
+                fixture("some.dir/some.dir.xx.0001.jpg")
+            ),
+            result[0]
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use crate::error::Error;
+use crate::utils::dir::{self, DirExt, FilesInDir};
+use crate::utils::extension::Naming;
+use crate::utils::file::PathExt;
+use crate::utils::types::RenameActions;
+use anyhow::anyhow;
+use camino::{Utf8Path, Utf8PathBuf};
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+// Breaks one managed directory into several new ones, alongside it. Files can
+// be grouped every so many files (in sequence order), wherever there is a long
+// enough gap between the modification times of consecutive files, or by
+// whether or not they are tagged. Each new directory is named from a pattern,
+// and the files in it are renamed to fit its name. The original directory is
+// removed if it ends up empty.
+
+#[derive(Debug, Clone, Copy)]
+pub enum SplitBy {
+    Count(usize),
+    Gap(Duration),
+    Tag,
+}
+
+pub type Group = (Utf8PathBuf, Vec<Utf8PathBuf>);
+
+// Turns "30", "30s", "10m", "6h" or "2d" into a duration.
+pub fn parse_gap(gap: &str) -> Result<Duration, String> {
+    let (num, unit) = gap.split_at(gap.find(|c: char| !c.is_ascii_digit()).unwrap_or(gap.len()));
+
+    let multiplier = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => return Err(format!("unknown unit '{}'. Use s, m, h or d", unit)),
+    };
+
+    match num.parse::<u64>() {
+        Ok(num) => match num.checked_mul(multiplier) {
+            Some(secs) => Ok(Duration::from_secs(secs)),
+            None => Err(format!("gap '{}' too large", gap)),
+        },
+        Err(_) => Err(format!("cannot parse '{}'", gap)),
+    }
+}
+
+// The new directory name for group number n.
+fn dir_name(pattern: &str, dir_basename: &str, n: usize) -> String {
+    pattern
+        .replace("{dir}", dir_basename)
+        .replace("{n}", &n.to_string())
+}
+
+// Works out which files go in which new directory.
+pub fn groups(
+    dir: &Utf8Path,
+    split_by: SplitBy,
+    pattern: &str,
+    naming: &Naming,
+) -> anyhow::Result<Vec<Group>> {
+    if !pattern.contains("{n}") {
+        return Err(
+            Error::InvalidInput("directory name pattern must contain {n}".to_string()).into(),
+        );
+    }
+
+    let files = dir.categorise_files(naming)?;
+    let parent = dir
+        .parent()
+        .ok_or(anyhow!("cannot get parent of {}", dir))?;
+    let dir_basename = dir::basename(dir)?;
+
+    let file_groups = match split_by {
+        SplitBy::Count(0) => {
+            return Err(Error::InvalidInput("cannot split every 0 files".to_string()).into());
+        }
+        SplitBy::Count(count) => in_sequence(&files)
+            .chunks(count)
+            .map(|chunk| chunk.to_vec())
+            .collect(),
+        SplitBy::Gap(gap) => split_on_gap(&files, gap)?,
+        SplitBy::Tag => vec![
+            untagged_files(&files),
+            files
+                .tagged
+                .numbered_files
+                .iter()
+                .chain(files.tagged.rogue_files.iter())
+                .cloned()
+                .collect(),
+        ],
+    };
+
+    let mut ret: Vec<Group> = Vec::new();
+
+    for group in file_groups.into_iter().filter(|g| !g.is_empty()) {
+        let new_dir = parent.join(dir_name(pattern, &dir_basename, ret.len() + 1));
+
+        if new_dir.exists() {
+            return Err(Error::Exists(new_dir).into());
+        }
+
+        ret.push((new_dir, group));
+    }
+
+    Ok(ret)
+}
+
+fn untagged_files(files: &FilesInDir) -> Vec<Utf8PathBuf> {
+    files
+        .untagged
+        .numbered_files
+        .iter()
+        .chain(files.untagged.rogue_files.iter())
+        .cloned()
+        .collect()
+}
+
+// All the files, numbered ones first, in number order, then the rogues.
+// Tagged and untagged files with the same number go together.
+fn in_sequence(files: &FilesInDir) -> Vec<Utf8PathBuf> {
+    let mut numbered: Vec<(i32, &Utf8PathBuf)> = files
+        .untagged
+        .numbered_files
+        .iter()
+        .chain(files.tagged.numbered_files.iter())
+        .map(|f| (f.get_number(&files.naming).unwrap_or(0), f))
+        .collect();
+
+    numbered.sort();
+
+    numbered
+        .into_iter()
+        .map(|(_num, f)| f.clone())
+        .chain(files.untagged.rogue_files.iter().cloned())
+        .chain(files.tagged.rogue_files.iter().cloned())
+        .collect()
+}
+
+fn split_on_gap(files: &FilesInDir, gap: Duration) -> anyhow::Result<Vec<Vec<Utf8PathBuf>>> {
+    let mut by_age: Vec<(SystemTime, Utf8PathBuf)> = Vec::new();
+
+    for file in in_sequence(files) {
+        by_age.push((fs::metadata(&file)?.modified()?, file));
+    }
+
+    by_age.sort();
+
+    let mut ret: Vec<Vec<Utf8PathBuf>> = Vec::new();
+    let mut last_mtime: Option<SystemTime> = None;
+
+    for (mtime, file) in by_age {
+        let new_group = match last_mtime {
+            Some(last) => mtime.duration_since(last).unwrap_or_default() > gap,
+            None => true,
+        };
+
+        if new_group {
+            ret.push(Vec::new());
+        }
+
+        if let Some(group) = ret.last_mut() {
+            group.push(file);
+        }
+
+        last_mtime = Some(mtime);
+    }
+
+    Ok(ret)
+}
+
+// Renames every file into its new directory, numbering each sequence from 1.
+pub(crate) fn actions(groups: &[Group], naming: &Naming) -> RenameActions {
+    let mut ret: RenameActions = Vec::new();
+
+    for (new_dir, files) in groups {
+        let basename = new_dir.file_name().unwrap_or_default();
+        let target = FilesInDir::new(new_dir.clone(), basename, naming);
+        let (mut untagged_num, mut tagged_num) = (0, 0);
+
+        for file in files {
+            let dest = if file.is_tagged(naming) {
+                tagged_num += 1;
+                target.tagged.fname_from_stem(file, tagged_num)
+            } else {
+                untagged_num += 1;
+                target.untagged.fname_from_stem(file, untagged_num)
+            };
+
+            ret.push((file.clone(), dest));
+        }
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_utils::fixture;
+
+    #[test]
+    fn test_parse_gap() {
+        assert_eq!(Duration::from_secs(30), parse_gap("30").unwrap());
+        assert_eq!(Duration::from_secs(30), parse_gap("30s").unwrap());
+        assert_eq!(Duration::from_secs(600), parse_gap("10m").unwrap());
+        assert_eq!(Duration::from_secs(21600), parse_gap("6h").unwrap());
+        assert_eq!(Duration::from_secs(172800), parse_gap("2d").unwrap());
+        assert!(parse_gap("2w").is_err());
+        assert!(parse_gap("h").is_err());
+        assert!(parse_gap("300000000000000d").is_err());
+    }
+
+    #[test]
+    fn test_dir_name() {
+        assert_eq!("some.dir.3", dir_name("{dir}.{n}", "some.dir", 3));
+        assert_eq!("part-1", dir_name("part-{n}", "some.dir", 1));
+    }
+
+    #[test]
+    fn test_groups_by_count() {
+        let result = groups(
+            &fixture("sorted.dir"),
+            SplitBy::Count(4),
+            "{dir}.{n}",
+            &Naming::new("xx"),
+        )
+        .unwrap();
+
+        assert_eq!(3, result.len());
+        assert_eq!(fixture("sorted.dir.1"), result[0].0);
+        assert_eq!(fixture("sorted.dir.3"), result[2].0);
+
+        assert_eq!(
+            vec![
+                fixture("sorted.dir/sorted.dir.0001.jpg"),
+                fixture("sorted.dir/sorted.dir.xx.0001.jpg"),
+                fixture("sorted.dir/sorted.dir.0002.jpg"),
+                fixture("sorted.dir/sorted.dir.xx.0002.jpg"),
+            ],
+            result[0].1
+        );
+
+        assert_eq!(2, result[2].1.len());
+
+        assert!(
+            groups(
+                &fixture("sorted.dir"),
+                SplitBy::Count(0),
+                "{dir}.{n}",
+                &Naming::new("xx")
+            )
+            .is_err()
+        );
+        assert!(
+            groups(
+                &fixture("sorted.dir"),
+                SplitBy::Count(2),
+                "{dir}",
+                &Naming::new("xx")
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_groups_by_tag() {
+        let result = groups(
+            &fixture("sorted.dir"),
+            SplitBy::Tag,
+            "part.{n}",
+            &Naming::new("xx"),
+        )
+        .unwrap();
+
+        assert_eq!(2, result.len());
+        assert_eq!(fixture("part.1"), result[0].0);
+        assert!(result[0].1.iter().all(|f| !f.is_tagged(&Naming::new("xx"))));
+        assert!(result[1].1.iter().all(|f| f.is_tagged(&Naming::new("xx"))));
+    }
+
+    #[test]
+    fn test_actions() {
+        let groups: Vec<Group> = vec![
+            (
+                fixture("new.1"),
+                vec![
+                    fixture("sorted.dir/sorted.dir.0001.jpg"),
+                    fixture("sorted.dir/sorted.dir.xx.0001.jpg"),
+                    fixture("sorted.dir/sorted.dir.0002.jpg"),
+                ],
+            ),
+            (
+                fixture("new.2"),
+                vec![fixture("sorted.dir/sorted.dir.0003.jpg")],
+            ),
+        ];
+
+        assert_eq!(
+            vec![
+                (
+                    fixture("sorted.dir/sorted.dir.0001.jpg"),
+                    fixture("new.1/new.1.0001.jpg")
+                ),
+                (
+                    fixture("sorted.dir/sorted.dir.xx.0001.jpg"),
+                    fixture("new.1/new.1.xx.0001.jpg")
+                ),
+                (
+                    fixture("sorted.dir/sorted.dir.0002.jpg"),
+                    fixture("new.1/new.1.0002.jpg")
+                ),
+                (
+                    fixture("sorted.dir/sorted.dir.0003.jpg"),
+                    fixture("new.2/new.2.0001.jpg")
+                ),
+            ],
+            actions(&groups, &Naming::new("xx"))
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use crate::utils::dir::{DirExt, FilesInDirSubtype};
+use crate::utils::extension::Naming;
+use crate::utils::file::PathExt;
+use crate::utils::journal;
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Serialize;
+use std::fs;
+
+// Reports on the health of the sequencing in a directory, without changing
+// anything. Reports can be serialized as JSON, or made human-readable.
+
+#[derive(Debug, Serialize)]
+pub struct DirStatus {
+    pub dir: Utf8PathBuf,
+    pub interrupted: bool,
+    pub untagged: SequenceStatus,
+    pub tagged: SequenceStatus,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SequenceStatus {
+    pub basename: String,
+    pub numbered: usize,
+    pub lowest: Option<i32>,
+    pub highest: Option<i32>,
+    pub holes: Vec<i32>,
+    pub duplicates: Vec<i32>,
+    pub rogues: Vec<Utf8PathBuf>,
+    pub misordered: Vec<Utf8PathBuf>,
+}
+
+impl SequenceStatus {
+    fn new(files: &FilesInDirSubtype) -> anyhow::Result<Self> {
+        Ok(SequenceStatus {
+            basename: files.basename.clone(),
+            numbered: files.numbered_files.len(),
+            lowest: files.numbers.first().copied(),
+            highest: files.numbers.last().copied(),
+            holes: files.hole_list(),
+            duplicates: duplicates(&files.numbers),
+            rogues: files.rogue_files.clone(),
+            misordered: misordered(&files.numbered_files, &files.naming)?,
+        })
+    }
+
+    pub fn needs_consolidating(&self) -> bool {
+        !(self.holes.is_empty() && self.duplicates.is_empty() && self.rogues.is_empty())
+    }
+}
+
+impl DirStatus {
+    pub fn needs_consolidating(&self) -> bool {
+        self.untagged.needs_consolidating() || self.tagged.needs_consolidating()
+    }
+}
+
+pub fn status(dir: &Utf8Path, naming: &Naming) -> anyhow::Result<DirStatus> {
+    let files = dir.categorise_files(naming)?;
+
+    Ok(DirStatus {
+        dir: dir.to_path_buf(),
+        interrupted: journal::journal_path(dir).exists(),
+        untagged: SequenceStatus::new(&files.untagged)?,
+        tagged: SequenceStatus::new(&files.tagged)?,
+    })
+}
+
+// Numbers which are used by more than one file. Expects a sorted list.
+fn duplicates(numbers: &[i32]) -> Vec<i32> {
+    let mut ret: Vec<i32> = numbers
+        .windows(2)
+        .filter(|pair| pair[0] == pair[1])
+        .map(|pair| pair[0])
+        .collect();
+
+    ret.dedup();
+    ret
+}
+
+// Files whose position in the number sequence differs from their position when
+// sorted by modification time. Numbers can outgrow their padding, so we sort by
+// the numbers themselves rather than by name.
+fn misordered(files: &[Utf8PathBuf], naming: &Naming) -> anyhow::Result<Vec<Utf8PathBuf>> {
+    let mut by_num = Vec::with_capacity(files.len());
+
+    for file in files {
+        by_num.push((
+            file.get_number(naming),
+            fs::metadata(file)?.modified()?,
+            file,
+        ));
+    }
+
+    by_num.sort_by_key(|(num, _mtime, file)| (*num, *file));
+
+    let mut by_age = by_num.clone();
+    by_age.sort_by_key(|(num, mtime, file)| (*mtime, *num, *file));
+
+    Ok(by_num
+        .iter()
+        .zip(by_age)
+        .filter(|((_, _, by_num), (_, _, by_age))| by_num != by_age)
+        .map(|((_, _, by_num), _)| (*by_num).clone())
+        .collect())
+}
+
+// Collapses a sorted list of numbers into ranges, like "1, 5-9, 12".
+fn ranges(numbers: &[i32]) -> String {
+    let mut spans: Vec<(i32, i32)> = Vec::new();
+
+    for &num in numbers {
+        match spans.last_mut() {
+            Some((_first, last)) if *last + 1 == num => *last = num,
+            _ => spans.push((num, num)),
+        }
+    }
+
+    let ret: Vec<String> = spans
+        .iter()
+        .map(|(first, last)| {
+            if first == last {
+                first.to_string()
+            } else {
+                format!("{}-{}", first, last)
+            }
+        })
+        .collect();
+
+    if ret.is_empty() {
+        "none".to_string()
+    } else {
+        ret.join(", ")
+    }
+}
+
+impl DirStatus {
+    // The human-readable report. verbose lists the rogue and out of order
+    // files.
+    pub fn report(&self, verbose: bool) -> String {
+        let summary = if self.interrupted {
+            "interrupted run, needs recovery"
+        } else if self.needs_consolidating() {
+            "needs consolidating"
+        } else {
+            "ok"
+        };
+
+        format!(
+            "{}: {}\n{}{}",
+            self.dir,
+            summary,
+            self.untagged.report(verbose),
+            self.tagged.report(verbose)
+        )
+    }
+}
+
+impl SequenceStatus {
+    fn report(&self, verbose: bool) -> String {
+        let range = match (self.lowest, self.highest) {
+            (Some(lowest), Some(highest)) => format!("{}-{}", lowest, highest),
+            _ => "none".to_string(),
+        };
+
+        let mut ret = format!(
+            "  {}: {} numbered ({}), {} rogue, holes: {}, duplicates: {}, out of age order: {}\n",
+            self.basename,
+            self.numbered,
+            range,
+            self.rogues.len(),
+            ranges(&self.holes),
+            ranges(&self.duplicates),
+            self.misordered.len(),
+        );
+
+        if verbose {
+            self.rogues
+                .iter()
+                .for_each(|f| ret.push_str(&format!("    rogue: {}\n", f)));
+            self.misordered
+                .iter()
+                .for_each(|f| ret.push_str(&format!("    out of order: {}\n", f)));
+        }
+
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+    use std::time::{Duration, SystemTime};
+    use test_utils::{fixture, fixture_dir};
+
+    #[test]
+    fn test_status() {
+        let result = status(&fixture("some.dir"), &Naming::new("tag")).unwrap();
+
+        assert!(!result.interrupted);
+        assert!(result.needs_consolidating());
+        assert_eq!(4, result.untagged.numbered);
+        assert_eq!(Some(1), result.untagged.lowest);
+        assert_eq!(Some(5), result.untagged.highest);
+        assert_eq!(vec![4], result.untagged.holes);
+        assert!(result.untagged.duplicates.is_empty());
+        assert_eq!(2, result.untagged.rogues.len());
+        assert_eq!(1, result.tagged.rogues.len());
+        assert_eq!(Some(1234), result.tagged.highest);
+
+        let result = status(&fixture("sorted.dir"), &Naming::new("xx")).unwrap();
+        assert!(!result.needs_consolidating());
+
+        assert!(status(&fixture("no.such.dir"), &Naming::new("tag")).is_err());
+    }
+
+    #[test]
+    fn test_report() {
+        let (_tmp, dir) = fixture_dir(
+            "r.dir",
+            vec!["r.dir.0001.jpg", "r.dir.0003.jpg", "rogue.jpg"],
+        );
+        let result = status(&dir, &Naming::new("tag")).unwrap();
+
+        assert_eq!(
+            format!(
+                "{}: needs consolidating\n  \
+                 r.dir: 2 numbered (1-3), 1 rogue, holes: 2, duplicates: none, out of age order: 0\n  \
+                 r.dir.tag: 0 numbered (none), 0 rogue, holes: none, duplicates: none, out of age order: 0\n",
+                dir
+            ),
+            result.report(false)
+        );
+
+        assert!(
+            result
+                .report(true)
+                .contains(&format!("    rogue: {}\n", dir.join("rogue.jpg")))
+        );
+    }
+
+    #[test]
+    fn test_misordered() {
+        let names = ["x.0002.jpg", "x.9999.jpg", "x.10000.jpg"];
+        let (_tmp, dir) = fixture_dir("x", names.to_vec());
+        let files: Vec<Utf8PathBuf> = names.iter().map(|n| dir.join(n)).collect();
+        let epoch = SystemTime::UNIX_EPOCH;
+
+        for (i, file) in files.iter().enumerate() {
+            let mtime = epoch + Duration::from_secs(1000 * (i as u64 + 1));
+            File::options()
+                .write(true)
+                .open(file)
+                .unwrap()
+                .set_modified(mtime)
+                .unwrap();
+        }
+
+        let mut by_name = files.clone();
+        by_name.sort();
+        assert!(
+            misordered(&by_name, &Naming::new("tag"))
+                .unwrap()
+                .is_empty()
+        );
+
+        File::options()
+            .write(true)
+            .open(&files[0])
+            .unwrap()
+            .set_modified(epoch + Duration::from_secs(5000))
+            .unwrap();
+
+        assert_eq!(
+            vec![files[0].clone(), files[1].clone(), files[2].clone()],
+            misordered(&by_name, &Naming::new("tag")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_duplicates() {
+        assert!(duplicates(&[1, 2, 3]).is_empty());
+        assert_eq!(vec![2, 5], duplicates(&[1, 2, 2, 2, 3, 5, 5]));
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!("none", ranges(&[]));
+        assert_eq!("4", ranges(&[4]));
+        assert_eq!("1, 5-9, 12", ranges(&[1, 5, 6, 7, 8, 9, 12]));
+    }
+}
@@ -1,5 +0,0 @@
-use crate::utils::common;
-use crate::utils::dir::DirExt;
-use crate::utils::types::Opts;
-
-crate::file_tag_action!(run, set_tag);
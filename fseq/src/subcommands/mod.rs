@@ -1,5 +1,5 @@
 pub mod dir_consolidate;
+pub mod dir_merge;
 pub mod dir_num_by_age;
-pub mod file_flip;
-pub mod file_set;
-pub mod file_unset;
+pub mod dir_split;
+pub mod dir_status;
@@ -0,0 +1,115 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// Every directory beneath the given ones, in sorted order. The given
+// directories themselves are not included. Hidden directories are skipped, and
+// symlinks are not followed.
+pub fn dirs_under(dirlist: &[Utf8PathBuf]) -> anyhow::Result<Vec<Utf8PathBuf>> {
+    let mut ret: Vec<Utf8PathBuf> = Vec::new();
+
+    for dir in dirlist {
+        collect_dirs(dir, &mut ret)?;
+    }
+
+    ret.sort();
+    ret.dedup();
+    Ok(ret)
+}
+
+fn collect_dirs(dir: &Utf8Path, aggr: &mut Vec<Utf8PathBuf>) -> anyhow::Result<()> {
+    for entry in dir.read_dir_utf8()? {
+        let entry = entry?;
+
+        if entry.file_type()?.is_dir() && !entry.file_name().starts_with('.') {
+            aggr.push(entry.path().to_path_buf());
+            collect_dirs(entry.path(), aggr)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Runs func on every directory, spreading the work over as many threads as
+// there are CPUs. Results come back in the same order as the directories.
+pub fn in_parallel<T, F>(dirs: &[Utf8PathBuf], func: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(&Utf8Path) -> T + Sync,
+{
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(dirs.len());
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<T>>> = Mutex::new((0..dirs.len()).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+
+                    if index >= dirs.len() {
+                        break;
+                    }
+
+                    let result = func(&dirs[index]);
+
+                    if let Ok(mut results) = results.lock() {
+                        results[index] = Some(result);
+                    }
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap_or_default()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use camino_tempfile_ext::prelude::*;
+    use std::fs;
+
+    #[test]
+    fn test_dirs_under() {
+        let temp_dir = Utf8TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        for dir in ["a.dir/b.dir", "c.dir", ".hidden/d.dir"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+
+        fs::write(root.join("c.dir/file.txt"), "").unwrap();
+
+        assert_eq!(
+            vec![
+                root.join("a.dir"),
+                root.join("a.dir/b.dir"),
+                root.join("c.dir")
+            ],
+            dirs_under(&[root.to_path_buf()]).unwrap()
+        );
+
+        assert!(dirs_under(&[root.join("no/such/dir")]).is_err());
+    }
+
+    #[test]
+    fn test_in_parallel() {
+        let dirs: Vec<Utf8PathBuf> = (0..50).map(|i| Utf8PathBuf::from(i.to_string())).collect();
+        let result = in_parallel(&dirs, |dir| dir.as_str().parse::<i32>().unwrap() * 2);
+        let expected: Vec<i32> = (0..50).map(|i| i * 2).collect();
+
+        assert_eq!(expected, result);
+        assert!(in_parallel(&[], |dir| dir.to_string()).is_empty());
+    }
+}
@@ -1,41 +1,134 @@
-use crate::utils::types::{Opts, RenameActionsResult};
-use anyhow::anyhow;
-use common::verbose;
+use crate::error::Error;
+use crate::utils::journal::Journal;
+use crate::utils::types::RenameActions;
+use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::HashSet;
 use std::fs;
 
-pub fn take_actions(action_list: RenameActionsResult, opts: &Opts) -> anyhow::Result<()> {
-    let mut errs = 0;
-
-    match action_list {
-        Ok(actions) => {
-            for (src, dest) in actions.iter() {
-                verbose!(opts, "{} -> {}", src, dest);
-
-                if !opts.noop {
-                    if dest.exists() {
-                        eprintln!("ERROR: {} exists", dest);
-                        errs += 1;
-                    } else {
-                        match fs::rename(src, dest) {
-                            Ok(_) => (),
-                            Err(e) => {
-                                eprintln!("ERROR: {}", e);
-                                errs += 1;
-                            }
-                        }
-                    }
-                }
+// Checks the action list and journals it in dir, then carries it out. If
+// anything goes wrong part-way through, the journal is left in place for
+// `fseq dir recover`. Returns the number of renames.
+pub fn apply(dir: &Utf8Path, actions: RenameActions) -> anyhow::Result<usize> {
+    check_actions(&actions)?;
+
+    if actions.is_empty() {
+        return Ok(0);
+    }
+
+    execute(Journal::create(dir, &actions)?, dir)
+}
+
+// Carries out an action list in place of an interrupted run in dir, replacing
+// that run's journal.
+pub fn resume_actions(dir: &Utf8Path, actions: RenameActions) -> anyhow::Result<usize> {
+    check_actions(&actions)?;
+    execute(Journal::replace(dir, &actions)?, dir)
+}
+
+fn execute(mut journal: Journal, dir: &Utf8Path) -> anyhow::Result<usize> {
+    let actions = journal.actions.clone();
+
+    for (i, (src, dest)) in actions.iter().enumerate() {
+        if dest.exists() {
+            return Err(Error::Incomplete {
+                dir: dir.to_path_buf(),
+                cause: format!("{} exists", dest),
             }
+            .into());
         }
-        Err(e) => {
-            errs += 1;
-            eprintln!("ERROR: {}", e);
+
+        if let Err(e) = fs::rename(src, dest) {
+            return Err(Error::Incomplete {
+                dir: dir.to_path_buf(),
+                cause: e.to_string(),
+            }
+            .into());
         }
+
+        journal.record(i)?;
     }
 
-    if errs > 0 {
-        Err(anyhow!("{} action errors", errs))
-    } else {
-        Ok(())
+    journal.finish()?;
+    Ok(actions.len())
+}
+
+// Walks through the action list before anything is touched, making sure that
+// every source will exist and no destination (including the temporary names
+// used to break cycles) will be overwritten.
+pub fn check_actions(actions: &RenameActions) -> anyhow::Result<()> {
+    let mut vacated: HashSet<&Utf8PathBuf> = HashSet::new();
+    let mut filled: HashSet<&Utf8PathBuf> = HashSet::new();
+
+    let present = |file: &Utf8PathBuf, vacated: &HashSet<_>, filled: &HashSet<_>| {
+        filled.contains(file) || (file.exists() && !vacated.contains(file))
+    };
+
+    for (src, dest) in actions.iter() {
+        if !present(src, &vacated, &filled) {
+            return Err(Error::DoesNotExist(src.clone()).into());
+        }
+
+        if present(dest, &vacated, &filled) {
+            return Err(Error::Exists(dest.clone()).into());
+        }
+
+        filled.remove(src);
+        vacated.insert(src);
+        vacated.remove(dest);
+        filled.insert(dest);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::journal;
+    use test_utils::fixture_dir;
+
+    #[test]
+    fn test_check_actions() {
+        let (_tmp, dir) = fixture_dir(
+            "c.dir",
+            vec!["c.dir.0002.jpg", "c.dir.0003.jpg", "_c.dir.0003.jpg"],
+        );
+
+        let swap = vec![
+            (dir.join("c.dir.0003.jpg"), dir.join("_c.dir.0002.jpg")),
+            (dir.join("c.dir.0002.jpg"), dir.join("c.dir.0003.jpg")),
+            (dir.join("_c.dir.0002.jpg"), dir.join("c.dir.0002.jpg")),
+        ];
+
+        assert!(check_actions(&swap).is_ok());
+
+        let clashing_tmp_name = vec![
+            (dir.join("c.dir.0002.jpg"), dir.join("_c.dir.0003.jpg")),
+            (dir.join("c.dir.0003.jpg"), dir.join("c.dir.0002.jpg")),
+            (dir.join("_c.dir.0003.jpg"), dir.join("c.dir.0003.jpg")),
+        ];
+
+        assert!(check_actions(&clashing_tmp_name).is_err());
+
+        let missing_source = vec![(dir.join("c.dir.0001.jpg"), dir.join("c.dir.0004.jpg"))];
+        assert!(check_actions(&missing_source).is_err());
+    }
+
+    #[test]
+    fn test_apply_leaves_no_journal() {
+        let (_tmp, dir) = fixture_dir("c.dir", vec!["c.dir.0002.jpg", "c.dir.0003.jpg"]);
+
+        let swap = vec![
+            (dir.join("c.dir.0003.jpg"), dir.join("_c.dir.0002.jpg")),
+            (dir.join("c.dir.0002.jpg"), dir.join("c.dir.0003.jpg")),
+            (dir.join("_c.dir.0002.jpg"), dir.join("c.dir.0002.jpg")),
+        ];
+
+        assert_eq!(3, apply(&dir, swap).unwrap());
+        assert!(!journal::journal_path(&dir).exists());
+        assert_eq!(
+            "c.dir.0003.jpg",
+            fs::read_to_string(dir.join("c.dir.0002.jpg")).unwrap()
+        );
     }
 }
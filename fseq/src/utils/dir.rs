@@ -1,4 +1,9 @@
+use crate::error::Error;
+use crate::utils::extension::Naming;
 use crate::utils::file::PathExt;
+use crate::utils::journal;
+use crate::utils::move_list::make_move_list;
+use crate::utils::sidecar;
 use crate::utils::types::{FileTokens, RenameActions, RenameActionsResult};
 use anyhow::anyhow;
 use camino::{Utf8Path, Utf8PathBuf};
@@ -14,8 +19,8 @@ pub struct FileTokenMap {
 }
 
 pub trait DirExt {
-    fn categorise_files(&self, tag: String) -> anyhow::Result<FilesInDir>;
-    fn file_token_map(&self, tag: &str) -> anyhow::Result<FileTokenMap>;
+    fn categorise_files(&self, naming: &Naming) -> anyhow::Result<FilesInDir>;
+    fn file_token_map(&self, naming: &Naming) -> anyhow::Result<FileTokenMap>;
 }
 
 #[derive(Debug)]
@@ -25,22 +30,25 @@ pub struct FilesInDirSubtype {
     pub rogue_files: Vec<Utf8PathBuf>,
     pub numbered_files: Vec<Utf8PathBuf>,
     pub numbers: Vec<i32>,
+    pub naming: Naming,
 }
 
 #[derive(Debug)]
 pub struct FilesInDir {
     pub tagged: FilesInDirSubtype,
     pub untagged: FilesInDirSubtype,
+    pub naming: Naming,
 }
 
 impl FilesInDirSubtype {
-    fn new(dirname: Utf8PathBuf, basename: &str) -> Self {
+    fn new(dirname: Utf8PathBuf, basename: &str, naming: &Naming) -> Self {
         FilesInDirSubtype {
             dirname,
             basename: basename.to_string(),
             rogue_files: Vec::new(),
             numbered_files: Vec::new(),
             numbers: Vec::new(),
+            naming: naming.clone(),
         }
     }
     // Returns a list of unused numbers which will be used to rename files.
@@ -71,42 +79,63 @@ impl FilesInDirSubtype {
         }
     }
 
-    pub fn fname_from_stem(&self, file: &Utf8Path, num: i32) -> Utf8PathBuf {
-        let stem = format!("{}.{}", self.basename, pad_num(num));
+    // The numbered files which have the given number. Usually one, but
+    // there's nothing to stop two files sharing a number.
+    fn files_numbered(&self, num: i32) -> Vec<&Utf8PathBuf> {
+        self.numbered_files
+            .iter()
+            .filter(|f| f.get_number(&self.naming) == Some(num))
+            .collect()
+    }
 
-        let stem = match file.extension() {
-            Some(ext) => format!("{}.{}", stem, ext),
-            None => stem,
-        };
+    // Renames which bump every file from num upwards by one, stopping at the
+    // first hole.
+    fn shift_up_from(&self, num: i32) -> RenameActions {
+        let mut ret: RenameActions = Vec::new();
+        let mut i = num;
 
-        let fname = Utf8PathBuf::from(&stem);
+        while self.numbers.contains(&i) {
+            for file in self.files_numbered(i) {
+                ret.push((file.clone(), self.fname_from_stem(file, i + 1)));
+            }
+            i += 1;
+        }
+
+        ret
+    }
+
+    pub fn fname_from_stem(&self, file: &Utf8Path, num: i32) -> Utf8PathBuf {
+        let ext = sidecar::item_extension(file, &self.naming);
+        let fname = format!("{}.{}{}", self.basename, pad_num(num), ext);
         self.dirname.join(fname)
     }
 }
 
 impl FilesInDir {
-    fn new(dirname: Utf8PathBuf, dir_basename: &str, tag: &str) -> Self {
+    pub fn new(dirname: Utf8PathBuf, dir_basename: &str, naming: &Naming) -> Self {
         FilesInDir {
-            untagged: FilesInDirSubtype::new(dirname.clone(), dir_basename),
+            untagged: FilesInDirSubtype::new(dirname.clone(), dir_basename, naming),
             tagged: FilesInDirSubtype::new(
                 dirname.clone(),
-                format!("{}.{}", dir_basename, tag).as_str(),
+                format!("{}.{}", dir_basename, naming.tag).as_str(),
+                naming,
             ),
+            naming: naming.clone(),
         }
     }
 
-    pub fn flip_tag(&self, file: Utf8PathBuf, tag: &str) -> RenameActionsResult {
-        if file.is_tagged(tag) {
-            self.unset_tag(file, tag)
+    pub fn flip_tag(&self, file: Utf8PathBuf) -> RenameActionsResult {
+        if file.is_tagged(&self.naming) {
+            self.unset_tag(file)
         } else {
-            self.set_tag(file, tag)
+            self.set_tag(file)
         }
     }
 
-    pub fn set_tag(&self, file: Utf8PathBuf, tag: &str) -> RenameActionsResult {
+    pub fn set_tag(&self, file: Utf8PathBuf) -> RenameActionsResult {
         let mut ret: RenameActions = Vec::new();
 
-        if !file.is_tagged(tag) {
+        if !file.is_tagged(&self.naming) {
             let target = self.tagged.fname_from_stem(&file, self.tagged.first_slot());
             ret.push((file, target));
         }
@@ -114,10 +143,10 @@ impl FilesInDir {
         Ok(ret)
     }
 
-    pub fn unset_tag(&self, file: Utf8PathBuf, tag: &str) -> RenameActionsResult {
+    pub fn unset_tag(&self, file: Utf8PathBuf) -> RenameActionsResult {
         let mut ret: RenameActions = Vec::new();
 
-        if file.is_tagged(tag) {
+        if file.is_tagged(&self.naming) {
             let target = self
                 .untagged
                 .fname_from_stem(&file, self.untagged.first_slot());
@@ -126,6 +155,74 @@ impl FilesInDir {
 
         Ok(ret)
     }
+
+    fn sequence_for(&self, file: &Utf8Path) -> &FilesInDirSubtype {
+        if file.is_tagged(&self.naming) {
+            &self.tagged
+        } else {
+            &self.untagged
+        }
+    }
+
+    // Gives a file which is not yet part of the sequence the number num,
+    // shuffling up the files which already occupy that part of the sequence.
+    pub fn insert_at(&self, file: Utf8PathBuf, num: i32) -> RenameActionsResult {
+        let seq = self.sequence_for(&file);
+
+        if seq
+            .numbered_files
+            .iter()
+            .any(|f| sidecar::same_item(f, &file, &self.naming))
+        {
+            return Err(Error::AlreadyInSequence(file).into());
+        }
+
+        let mut ret = seq.shift_up_from(num);
+        ret.push((file.clone(), seq.fname_from_stem(&file, num)));
+        make_move_list(ret, &self.naming)
+    }
+
+    // Gives a file which is already part of the sequence the number num,
+    // closing the gap it leaves and shuffling along the files between. Every
+    // number between moves along by one, so any holes there move with them.
+    pub fn move_to(&self, file: Utf8PathBuf, num: i32) -> RenameActionsResult {
+        let seq = self.sequence_for(&file);
+
+        let file = match seq
+            .numbered_files
+            .iter()
+            .find(|f| sidecar::same_item(f, &file, &self.naming))
+        {
+            Some(listed) => listed.clone(),
+            None => return Err(Error::NotInSequence(file).into()),
+        };
+
+        let current = match file.get_number(&self.naming) {
+            Some(current) => current,
+            None => return Err(Error::NotInSequence(file).into()),
+        };
+
+        let mut ret: RenameActions = Vec::new();
+
+        if num == current {
+            return Ok(ret);
+        } else if num < current {
+            for i in num..current {
+                for f in seq.files_numbered(i) {
+                    ret.push((f.clone(), seq.fname_from_stem(f, i + 1)));
+                }
+            }
+        } else {
+            for i in current + 1..=num {
+                for f in seq.files_numbered(i) {
+                    ret.push((f.clone(), seq.fname_from_stem(f, i - 1)));
+                }
+            }
+        }
+
+        ret.push((file.clone(), seq.fname_from_stem(&file, num)));
+        make_move_list(ret, &self.naming)
+    }
 }
 
 pub fn basename<P: AsRef<Utf8Path>>(path: P) -> anyhow::Result<String> {
@@ -137,41 +234,51 @@ pub fn basename<P: AsRef<Utf8Path>>(path: P) -> anyhow::Result<String> {
     }
 }
 
+// One file for each item in the directory, leaving out sidecars, which are
+// renamed along with the file they belong to.
+fn item_files(dir: &Utf8Path, naming: &Naming) -> anyhow::Result<Vec<Utf8PathBuf>> {
+    let mut ret: Vec<Utf8PathBuf> = Vec::new();
+
+    for file in dir.read_dir_utf8()? {
+        let file = file?;
+        let path = file.path();
+
+        if !path.is_dir() && !journal::is_journal(path) {
+            ret.push(path.to_path_buf());
+        }
+    }
+
+    Ok(sidecar::primaries(ret, naming))
+}
+
 impl DirExt for Utf8Path {
-    fn categorise_files(&self, tag: String) -> anyhow::Result<FilesInDir> {
+    fn categorise_files(&self, naming: &Naming) -> anyhow::Result<FilesInDir> {
         let dir_basename = basename(self)?;
-        let mut ret = FilesInDir::new(self.to_path_buf(), dir_basename.as_str(), &tag);
+        let mut ret = FilesInDir::new(self.to_path_buf(), dir_basename.as_str(), naming);
 
         let pattern = format!(
-            r"^{}(\.{})?\.\d+\.\w+$",
+            r"^{}(\.{})?\.\d+$",
             regex::escape(&dir_basename),
-            regex::escape(&tag)
+            regex::escape(&naming.tag)
         );
         let rx = Regex::new(&pattern).unwrap();
 
-        for file in self.read_dir_utf8()? {
-            let file = file?;
-            let path = file.path();
+        for path in item_files(self, naming)? {
+            let path = path.as_path();
 
-            if path.is_dir() {
-                continue;
-            }
-
-            let file_basename = basename(path)?;
-
-            if rx.is_match(&file_basename) {
-                if path.is_tagged(&tag) {
+            if rx.is_match(sidecar::item_key(path, naming)) {
+                if path.is_tagged(naming) {
                     ret.tagged.numbered_files.push(path.to_path_buf());
-                    if let Some(num) = path.get_number() {
+                    if let Some(num) = path.get_number(naming) {
                         ret.tagged.numbers.push(num);
                     }
                 } else {
                     ret.untagged.numbered_files.push(path.to_path_buf());
-                    if let Some(num) = path.get_number() {
+                    if let Some(num) = path.get_number(naming) {
                         ret.untagged.numbers.push(num);
                     }
                 }
-            } else if path.is_tagged(&tag) {
+            } else if path.is_tagged(naming) {
                 ret.tagged.rogue_files.push(path.to_path_buf());
             } else {
                 ret.untagged.rogue_files.push(path.to_path_buf());
@@ -188,22 +295,17 @@ impl DirExt for Utf8Path {
         Ok(ret)
     }
 
-    fn file_token_map(&self, tag: &str) -> anyhow::Result<FileTokenMap> {
+    fn file_token_map(&self, naming: &Naming) -> anyhow::Result<FileTokenMap> {
         let mut ret = FileTokenMap {
             tagged: HashMap::new(),
             untagged: HashMap::new(),
         };
 
-        for file in self.read_dir_utf8()? {
-            let file = file?;
-            let path = file.path();
-
-            if path.is_dir() {
-                continue;
-            }
+        for path in item_files(self, naming)? {
+            let path = path.as_path();
 
-            if let Ok(tokens) = FileTokens::new(path, tag) {
-                if path.is_tagged(tag) {
+            if let Ok(tokens) = FileTokens::new(path, naming) {
+                if path.is_tagged(naming) {
                     ret.tagged.insert(path.to_owned(), tokens);
                 } else {
                     ret.untagged.insert(path.to_owned(), tokens);
@@ -223,24 +325,26 @@ pub fn pad_num(num: i32) -> String {
 #[cfg(test)]
 mod test {
     use super::*;
-    use test_utils::fixture;
+    use crate::utils::common;
+    use std::fs;
+    use test_utils::{fixture, fixture_dir};
 
     #[test]
     fn test_file_token_map() {
-        let result = fixture("some.dir").file_token_map("tag").unwrap();
+        let result = fixture("some.dir").file_token_map(&Naming::new("tag")).unwrap();
 
         assert_eq!(5, result.tagged.len());
         assert_eq!(4, result.untagged.len());
 
         assert!(Utf8PathBuf::from("test/no/such/dir")
-            .file_token_map("tag")
+            .file_token_map(&Naming::new("tag"))
             .is_err());
     }
 
     #[test]
     fn test_set_tag() {
         let t = fixture("some.dir")
-            .categorise_files("tag".to_string())
+            .categorise_files(&Naming::new("tag"))
             .unwrap();
 
         assert_eq!(
@@ -248,7 +352,7 @@ mod test {
                 fixture("some.dir/some.dir.0001.jpg"),
                 fixture("some.dir/some.dir.tag.0001.jpg")
             )],
-            t.set_tag(fixture("some.dir/some.dir.0001.jpg"), "tag",)
+            t.set_tag(fixture("some.dir/some.dir.0001.jpg"))
                 .unwrap(),
         );
 
@@ -257,7 +361,7 @@ mod test {
                 fixture("some.dir/some.dir.0004.jpg"),
                 fixture("some.dir/some.dir.tag.0001.jpg")
             )],
-            t.set_tag(fixture("some.dir/some.dir.0004.jpg"), "tag",)
+            t.set_tag(fixture("some.dir/some.dir.0004.jpg"))
                 .unwrap(),
         );
 
@@ -266,11 +370,11 @@ mod test {
                 fixture("some.dir/whatever.JPG"),
                 fixture("some.dir/some.dir.tag.0001.JPG")
             )],
-            t.set_tag(fixture("some.dir/whatever.JPG"), "tag",).unwrap(),
+            t.set_tag(fixture("some.dir/whatever.JPG")).unwrap(),
         );
 
         assert!(t
-            .set_tag(fixture("some.dir/some.dir.tag.0004.jpg"), "tag",)
+            .set_tag(fixture("some.dir/some.dir.tag.0004.jpg"))
             .unwrap()
             .is_empty(),);
     }
@@ -278,7 +382,7 @@ mod test {
     #[test]
     fn test_flip_tag() {
         let t = fixture("some.dir")
-            .categorise_files("tag".to_string())
+            .categorise_files(&Naming::new("tag"))
             .unwrap();
 
         assert_eq!(
@@ -286,7 +390,7 @@ mod test {
                 fixture("some.dir/some.dir.0001.jpg"),
                 fixture("some.dir/some.dir.tag.0001.jpg")
             )],
-            t.flip_tag(fixture("some.dir/some.dir.0001.jpg"), "tag",)
+            t.flip_tag(fixture("some.dir/some.dir.0001.jpg"))
                 .unwrap(),
         );
 
@@ -295,7 +399,7 @@ mod test {
                 fixture("some.dir/some.dir.tag.0004.jpg"),
                 fixture("some.dir/some.dir.0004.jpg")
             )],
-            t.flip_tag(fixture("some.dir/some.dir.tag.0004.jpg"), "tag",)
+            t.flip_tag(fixture("some.dir/some.dir.tag.0004.jpg"))
                 .unwrap(),
         );
 
@@ -304,7 +408,7 @@ mod test {
                 fixture("some.dir/whatever.JPG"),
                 fixture("some.dir/some.dir.tag.0001.JPG")
             )],
-            t.flip_tag(fixture("some.dir/whatever.JPG"), "tag",)
+            t.flip_tag(fixture("some.dir/whatever.JPG"))
                 .unwrap(),
         );
     }
@@ -312,7 +416,7 @@ mod test {
     #[test]
     fn test_unset_tag() {
         let t = fixture("some.dir")
-            .categorise_files("tag".to_string())
+            .categorise_files(&Naming::new("tag"))
             .unwrap();
 
         assert_eq!(
@@ -320,7 +424,7 @@ mod test {
                 fixture("some.dir/some.dir.tag.0004.jpg"),
                 fixture("some.dir/some.dir.0004.jpg")
             )],
-            t.unset_tag(fixture("some.dir/some.dir.tag.0004.jpg"), "tag",)
+            t.unset_tag(fixture("some.dir/some.dir.tag.0004.jpg"))
                 .unwrap(),
         );
 
@@ -329,20 +433,165 @@ mod test {
                 fixture("some.dir/whatever.tag.55.JPG"),
                 fixture("some.dir/some.dir.0004.JPG")
             )],
-            t.unset_tag(fixture("some.dir/whatever.tag.55.JPG"), "tag",)
+            t.unset_tag(fixture("some.dir/whatever.tag.55.JPG"))
                 .unwrap(),
         );
 
         assert!(t
-            .unset_tag(fixture("some.dir/some.dir.0001.jpg"), "tag",)
+            .unset_tag(fixture("some.dir/some.dir.0001.jpg"))
             .unwrap()
             .is_empty(),);
     }
 
+    #[test]
+    fn test_insert_at() {
+        let t = fixture("some.dir")
+            .categorise_files(&Naming::new("tag"))
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                (
+                    fixture("some.dir/some.dir.0002.jpg"),
+                    fixture("some.dir/_some.dir.0003.jpg")
+                ),
+                (
+                    fixture("some.dir/some.dir.0003.jpg"),
+                    fixture("some.dir/some.dir.0004.jpg")
+                ),
+                (
+                    fixture("some.dir/other_random_name.jpg"),
+                    fixture("some.dir/some.dir.0002.jpg")
+                ),
+                (
+                    fixture("some.dir/_some.dir.0003.jpg"),
+                    fixture("some.dir/some.dir.0003.jpg")
+                ),
+            ],
+            t.insert_at(fixture("some.dir/other_random_name.jpg"), 2)
+                .unwrap(),
+        );
+
+        assert_eq!(
+            vec![(
+                fixture("some.dir/other_random_name.jpg"),
+                fixture("some.dir/some.dir.0004.jpg")
+            )],
+            t.insert_at(fixture("some.dir/other_random_name.jpg"), 4)
+                .unwrap(),
+        );
+
+        assert!(
+            t.insert_at(fixture("some.dir/some.dir.0002.jpg"), 1)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_move_to() {
+        let t = fixture("some.dir")
+            .categorise_files(&Naming::new("tag"))
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                (
+                    fixture("some.dir/some.dir.0001.jpg"),
+                    fixture("some.dir/_some.dir.0002.jpg")
+                ),
+                (
+                    fixture("some.dir/some.dir.0002.jpg"),
+                    fixture("some.dir/_some.dir.0003.jpg")
+                ),
+                (
+                    fixture("some.dir/some.dir.0003.jpg"),
+                    fixture("some.dir/some.dir.0004.jpg")
+                ),
+                (
+                    fixture("some.dir/some.dir.0005.jpg"),
+                    fixture("some.dir/some.dir.0001.jpg")
+                ),
+                (
+                    fixture("some.dir/_some.dir.0002.jpg"),
+                    fixture("some.dir/some.dir.0002.jpg")
+                ),
+                (
+                    fixture("some.dir/_some.dir.0003.jpg"),
+                    fixture("some.dir/some.dir.0003.jpg")
+                ),
+            ],
+            t.move_to(fixture("some.dir/some.dir.0005.jpg"), 1).unwrap(),
+        );
+
+        assert_eq!(
+            vec![
+                (
+                    fixture("some.dir/some.dir.0002.jpg"),
+                    fixture("some.dir/_some.dir.0001.jpg")
+                ),
+                (
+                    fixture("some.dir/some.dir.0003.jpg"),
+                    fixture("some.dir/some.dir.0002.jpg")
+                ),
+                (
+                    fixture("some.dir/some.dir.0001.jpg"),
+                    fixture("some.dir/some.dir.0003.jpg")
+                ),
+                (
+                    fixture("some.dir/_some.dir.0001.jpg"),
+                    fixture("some.dir/some.dir.0001.jpg")
+                ),
+            ],
+            t.move_to(fixture("some.dir/some.dir.0001.jpg"), 3).unwrap(),
+        );
+
+        assert!(
+            t.move_to(fixture("some.dir/some.dir.0002.jpg"), 2)
+                .unwrap()
+                .is_empty()
+        );
+
+        assert!(t.move_to(fixture("some.dir/random_name.jpg"), 2).is_err());
+    }
+
+    #[test]
+    fn test_move_to_across_hole() {
+        let files = vec![
+            "g.dir.0001.jpg",
+            "g.dir.0002.jpg",
+            "g.dir.0004.jpg",
+            "g.dir.0005.jpg",
+        ];
+
+        // Moving up a file, the hole moves up with the files it passes.
+        let (_tmp, dir) = fixture_dir("g.dir", files.clone());
+        let t = dir.categorise_files(&Naming::new("tag")).unwrap();
+        common::apply(&dir, t.move_to(dir.join("g.dir.0005.jpg"), 1).unwrap()).unwrap();
+
+        let t = dir.categorise_files(&Naming::new("tag")).unwrap();
+        assert_eq!(vec![4], t.untagged.hole_list());
+        assert_eq!(
+            "g.dir.0004.jpg",
+            fs::read_to_string(dir.join("g.dir.0005.jpg")).unwrap()
+        );
+
+        // Moving down a file, it moves down with them.
+        let (_tmp, dir) = fixture_dir("g.dir", files);
+        let t = dir.categorise_files(&Naming::new("tag")).unwrap();
+        common::apply(&dir, t.move_to(dir.join("g.dir.0001.jpg"), 5).unwrap()).unwrap();
+
+        let t = dir.categorise_files(&Naming::new("tag")).unwrap();
+        assert_eq!(vec![2], t.untagged.hole_list());
+        assert_eq!(
+            "g.dir.0004.jpg",
+            fs::read_to_string(dir.join("g.dir.0003.jpg")).unwrap()
+        );
+    }
+
     #[test]
     fn test_hole_list() {
         let t = fixture("some.dir")
-            .categorise_files("tag".to_string())
+            .categorise_files(&Naming::new("tag"))
             .unwrap();
 
         assert_eq!(vec![4], t.untagged.hole_list());
@@ -353,7 +602,7 @@ mod test {
 
     #[test]
     fn test_fname_from_stem() {
-        let t = FilesInDir::new(fixture("some.dir"), "some.dir", "tag");
+        let t = FilesInDir::new(fixture("some.dir"), "some.dir", &Naming::new("tag"));
 
         assert_eq!(
             fixture("some.dir/some.dir.0045.jpg"),
@@ -364,7 +613,7 @@ mod test {
     #[test]
     fn test_categorise_files() {
         let result = fixture("some.dir")
-            .categorise_files("tag".to_string())
+            .categorise_files(&Naming::new("tag"))
             .unwrap();
 
         assert_eq!("some.dir", result.untagged.basename);
@@ -408,10 +657,85 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_categorise_files_with_sidecars() {
+        let result = fixture("sidecar.dir")
+            .categorise_files(&Naming::new("tag"))
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                fixture("sidecar.dir/sidecar.dir.0001.cr2"),
+                fixture("sidecar.dir/sidecar.dir.0003.jpg"),
+            ],
+            result.untagged.numbered_files,
+        );
+
+        assert_eq!(vec![1, 3], result.untagged.numbers);
+
+        assert_eq!(
+            vec![
+                fixture("sidecar.dir/IMG_1234.cr2"),
+                fixture("sidecar.dir/orphan.jpg.xmp"),
+            ],
+            result.untagged.rogue_files,
+        );
+
+        assert_eq!(
+            fixture("sidecar.dir/sidecar.dir.0004.jpg.xmp"),
+            result
+                .untagged
+                .fname_from_stem(&fixture("sidecar.dir/orphan.jpg.xmp"), 4)
+        );
+    }
+
+    #[test]
+    fn test_categorise_files_extensions() {
+        let (_tmp, dir) = fixture_dir(
+            "ext.dir",
+            vec![
+                "ext.dir.0001",
+                "ext.dir.0002.tar.gz",
+                "ext.dir.tag.0001.tar.xz",
+                "ext.dir.0001.2",
+            ],
+        );
+
+        let result = dir.categorise_files(&Naming::new("tag")).unwrap();
+
+        // ext.dir.0001.2 is number 1, with the extension .2, so it collides
+        // with ext.dir.0001.
+        assert_eq!(
+            vec![
+                dir.join("ext.dir.0001"),
+                dir.join("ext.dir.0001.2"),
+                dir.join("ext.dir.0002.tar.gz")
+            ],
+            result.untagged.numbered_files
+        );
+
+        assert_eq!(vec![1, 1, 2], result.untagged.numbers);
+        assert_eq!(
+            vec![dir.join("ext.dir.tag.0001.tar.xz")],
+            result.tagged.numbered_files
+        );
+        assert!(result.untagged.rogue_files.is_empty());
+
+        // Names made from the scheme parse back to the same number.
+        for file in result.untagged.numbered_files.iter() {
+            let renamed = result.untagged.fname_from_stem(file, 7);
+            assert_eq!(Some(7), renamed.get_number(&result.naming));
+            assert_eq!(
+                sidecar::item_extension(file, &result.naming),
+                sidecar::item_extension(&renamed, &result.naming)
+            );
+        }
+    }
+
     #[test]
     fn test_categorise_files_2() {
         let result = fixture("some.dir")
-            .categorise_files("xx".to_string())
+            .categorise_files(&Naming::new("xx"))
             .unwrap();
 
         println!("{:#?}", result);
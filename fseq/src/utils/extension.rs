@@ -0,0 +1,147 @@
+use camino::Utf8Path;
+
+// Works out where a file name's extension starts. Usually that's the last dot,
+// but some extensions, like tar.gz, have more than one part, and files with no
+// extension at all can still be numbered, like dir.0001. Only a name which is
+// exactly its directory's name (and perhaps the tag) and a number is taken to
+// have no extension, so x.0003.001 keeps its .001.
+
+pub const COMPOUND_EXTENSIONS: &str = "tar.gz,tar.bz2,tar.xz,tar.zst";
+
+// How the names in a sequence are made: the tag, and which extensions have more
+// than one part.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Naming {
+    pub tag: String,
+    pub compound_exts: Vec<String>,
+}
+
+impl Naming {
+    pub fn new(tag: &str) -> Naming {
+        Naming {
+            tag: tag.to_string(),
+            compound_exts: Vec::new(),
+        }
+        .with_compound_exts(&COMPOUND_EXTENSIONS.split(',').collect::<Vec<&str>>())
+    }
+
+    // Replaces the list of compound extensions.
+    pub fn with_compound_exts<S: AsRef<str>>(mut self, extensions: &[S]) -> Naming {
+        self.compound_exts = extensions
+            .iter()
+            .map(|ext| ext.as_ref().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        self
+    }
+
+    // Splits a file's name into the part before the extension, and the
+    // extension with its leading dot. The extension is empty if there isn't
+    // one.
+    pub fn split_name<'a>(&self, file: &'a Utf8Path) -> (&'a str, &'a str) {
+        let name = file.file_name().unwrap_or_default();
+        let base = file.parent().and_then(|dir| dir.file_name());
+        self.split(name, base)
+    }
+
+    // split_name, for a name in the directory called base.
+    pub fn split<'a>(&self, name: &'a str, base: Option<&str>) -> (&'a str, &'a str) {
+        if base.is_some_and(|base| self.is_unsuffixed(name, base)) {
+            return (name, "");
+        }
+
+        let lower = name.to_lowercase();
+
+        for ext in self.compound_exts.iter() {
+            if lower.len() > ext.len() + 1 && lower.ends_with(&format!(".{}", ext)) {
+                return name.split_at(name.len() - ext.len() - 1);
+            }
+        }
+
+        match name.rfind('.') {
+            Some(i) if i > 0 && i < name.len() - 1 => name.split_at(i),
+            _ => (name, ""),
+        }
+    }
+
+    // Whether a name is <base>.<number> or <base>.<tag>.<number>, perhaps with
+    // the underscores of a temporary name in front.
+    fn is_unsuffixed(&self, name: &str, base: &str) -> bool {
+        let rest = match name.trim_start_matches('_').strip_prefix(base) {
+            Some(rest) => rest,
+            None => return false,
+        };
+
+        let number = match rest.strip_prefix('.') {
+            Some(rest) => rest
+                .strip_prefix(&format!("{}.", self.tag))
+                .filter(|_| !self.tag.is_empty())
+                .unwrap_or(rest),
+            None => return false,
+        };
+
+        !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn split(name: &str) -> (&str, &str) {
+        Naming::new("tag").split(name, Some("dir"))
+    }
+
+    #[test]
+    fn test_split_name() {
+        assert_eq!(("dir.0001", ".jpg"), split("dir.0001.jpg"));
+        assert_eq!(("dir.0001", ""), split("dir.0001"));
+        assert_eq!(("dir.tag.0001", ""), split("dir.tag.0001"));
+        assert_eq!(("_dir.0001", ""), split("_dir.0001"));
+        assert_eq!(("dir.0001", ".tar.gz"), split("dir.0001.tar.gz"));
+        assert_eq!(("dir.0001", ".TAR.GZ"), split("dir.0001.TAR.GZ"));
+        assert_eq!(("dir.0001.tar", ".7z"), split("dir.0001.tar.7z"));
+        assert_eq!(("no_extension", ""), split("no_extension"));
+        assert_eq!((".hidden", ""), split(".hidden"));
+        assert_eq!((".tar", ".gz"), split(".tar.gz"));
+        assert_eq!(("trailing.", ""), split("trailing."));
+    }
+
+    #[test]
+    fn test_split_name_numeric_extension() {
+        assert_eq!(("dir.0003", ".001"), split("dir.0003.001"));
+        assert_eq!(("x.0003", ".001"), split("x.0003.001"));
+        assert_eq!(("archive.7z", ".001"), split("archive.7z.001"));
+        assert_eq!(("dir.other", ".0001"), split("dir.other.0001"));
+        assert_eq!(("other", ".0001"), split("other.0001"));
+
+        // Without a directory, a number is always an extension.
+        assert_eq!(("dir", ".0001"), Naming::new("tag").split("dir.0001", None));
+
+        assert_eq!(
+            ("dir.0001", ""),
+            Naming::new("tag").split_name(Utf8Path::new("/a/dir/dir.0001"))
+        );
+    }
+
+    #[test]
+    fn test_compound_exts() {
+        let naming = Naming::new("tag").with_compound_exts(&[".pkg.zip", "", "TAR.GZ"]);
+
+        assert_eq!(vec!["pkg.zip", "tar.gz"], naming.compound_exts);
+        assert_eq!(
+            ("dir.0001", ".pkg.zip"),
+            naming.split("dir.0001.pkg.zip", None)
+        );
+        assert_eq!(
+            ("dir.0001.tar", ".xz"),
+            naming.split("dir.0001.tar.xz", None)
+        );
+
+        // Each Naming has its own list.
+        assert_eq!(
+            ("dir.0001", ".tar.xz"),
+            Naming::new("tag").split("dir.0001.tar.xz", None)
+        );
+    }
+}
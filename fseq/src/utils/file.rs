@@ -1,23 +1,25 @@
+use crate::utils::extension::Naming;
+use crate::utils::sidecar;
 use anyhow::anyhow;
 use camino::{Utf8Path, Utf8PathBuf};
 
 pub trait PathExt {
-    fn is_tagged(&self, tag: &str) -> bool;
-    fn fname_tokens(&self) -> anyhow::Result<Vec<String>>;
-    fn get_number(&self) -> Option<i32>;
+    fn is_tagged(&self, naming: &Naming) -> bool;
+    fn fname_tokens(&self, naming: &Naming) -> anyhow::Result<Vec<String>>;
+    fn get_number(&self, naming: &Naming) -> Option<i32>;
 }
 
 impl PathExt for Utf8Path {
-    fn is_tagged(&self, tag: &str) -> bool {
-        self.to_owned().is_tagged(tag)
+    fn is_tagged(&self, naming: &Naming) -> bool {
+        self.to_owned().is_tagged(naming)
     }
 
-    fn fname_tokens(&self) -> anyhow::Result<Vec<String>> {
-        self.to_owned().fname_tokens()
+    fn fname_tokens(&self, naming: &Naming) -> anyhow::Result<Vec<String>> {
+        self.to_owned().fname_tokens(naming)
     }
 
-    fn get_number(&self) -> Option<i32> {
-        self.to_owned().get_number()
+    fn get_number(&self, naming: &Naming) -> Option<i32> {
+        self.to_owned().get_number(naming)
     }
 }
 
@@ -27,7 +29,7 @@ impl PathExt for Utf8PathBuf {
     //
     // 1. Consider a file tagged if the tag appears as any part of its name.
     //
-    // 2. Consider a file tagged if the third token from the end is the tag
+    // 2. Consider a file tagged if the token before the number is the tag
     //    pattern, regardless of the rest of the name. This means
     //    we'll preserve tags when we consolidate stuff that's found its way
     //    in from a different directory. BUT, this means that if a directory
@@ -41,31 +43,35 @@ impl PathExt for Utf8PathBuf {
     //
     // I think (2) is probably the best approach.
 
-    fn is_tagged(&self, tag: &str) -> bool {
-        match self.fname_tokens() {
-            Ok(tokens) => tokens[tokens.len() - 3] == tag,
+    fn is_tagged(&self, naming: &Naming) -> bool {
+        match self.fname_tokens(naming) {
+            Ok(tokens) => tokens.len() > 2 && tokens[tokens.len() - 2] == naming.tag,
             Err(_) => false,
         }
     }
 
-    fn fname_tokens(&self) -> anyhow::Result<Vec<String>> {
-        let basename = match self.file_name() {
-            Some(name) => name.to_owned(),
-            None => return Err(anyhow!("Invalid file name")),
-        };
+    // The dot-separated parts of the name, not counting the extension, which
+    // may have more than one part, or be missing altogether.
+    fn fname_tokens(&self, naming: &Naming) -> anyhow::Result<Vec<String>> {
+        if self.file_name().is_none() {
+            return Err(anyhow!("Invalid file name"));
+        }
 
-        let tokens: Vec<String> = basename.split(".").map(|s| s.to_owned()).collect();
+        let tokens: Vec<String> = sidecar::item_key(self, naming)
+            .split(".")
+            .map(|s| s.to_owned())
+            .collect();
 
-        if tokens.len() < 3 {
+        if tokens.len() < 2 {
             return Err(anyhow!("Filename does not contain enough information"));
         }
 
         Ok(tokens)
     }
 
-    fn get_number(&self) -> Option<i32> {
-        let tokens = self.fname_tokens().unwrap_or_default();
-        tokens[tokens.len() - 2].parse::<i32>().ok()
+    fn get_number(&self, naming: &Naming) -> Option<i32> {
+        let tokens = self.fname_tokens(naming).ok()?;
+        tokens[tokens.len() - 1].parse::<i32>().ok()
     }
 }
 
@@ -74,30 +80,58 @@ mod test {
     use super::*;
     use test_utils::fixture;
 
+    fn naming() -> Naming {
+        Naming::new("tag")
+    }
+
     #[test]
     fn test_get_number() {
         assert_eq!(
             Some(1),
-            fixture("/path/to/some.dir/some.dir.0001.jpg").get_number()
+            fixture("/path/to/some.dir/some.dir.0001.jpg").get_number(&naming())
         );
 
         assert_eq!(
             Some(99),
-            fixture("/path/to/some.dir/some.dir.tag.0099.jpg").get_number()
+            fixture("/path/to/some.dir/some.dir.tag.0099.jpg").get_number(&naming())
         );
 
-        assert_eq!(None, fixture("/path/to/some.dir/some.dir.jpg").get_number());
+        assert_eq!(
+            None,
+            fixture("/path/to/some.dir/some.dir.jpg").get_number(&naming())
+        );
+        assert_eq!(
+            None,
+            fixture("/path/to/some.dir/random_name.jpg").get_number(&naming())
+        );
+        assert_eq!(
+            Some(3),
+            fixture("some.dir/some.dir.0003").get_number(&naming())
+        );
+        assert_eq!(Some(3), fixture("x/x.0003.001").get_number(&naming()));
+        assert_eq!(None, fixture("x/archive.7z.001").get_number(&naming()));
+        assert_eq!(
+            Some(3),
+            fixture("some.dir.0003.tar.gz").get_number(&naming())
+        );
+        assert_eq!(
+            Some(3),
+            fixture("some.dir.tag.0003.jpg.xmp").get_number(&naming())
+        );
     }
 
     #[test]
     fn test_is_tagged() {
-        assert!(fixture("some.dir.tag.0001.jpg").is_tagged("tag"));
-        assert!(fixture("some.dir.tag.0001.jpg").is_tagged("tag"));
-        assert!(fixture("/path/to/some.dir/some.dir.tag.0001.jpg").is_tagged("tag"));
-        assert!(!fixture("oo.tag.oo.123.png").is_tagged("tag"));
-        assert!(!fixture("/path/to/some.dir/some.dir.0001.jpg").is_tagged("tag"));
-        assert!(!fixture("/path/to/some.tag.dir/some.dir.0001.jpg").is_tagged("tag"));
-        assert!(!fixture("some.dir.0001.jpg").is_tagged("tag"));
-        assert!(!fixture("butagy_rabbit.jpg").is_tagged("tag"));
+        assert!(fixture("some.dir.tag.0001.jpg").is_tagged(&naming()));
+        assert!(fixture("some.dir.tag.0001.jpg").is_tagged(&naming()));
+        assert!(fixture("/path/to/some.dir/some.dir.tag.0001.jpg").is_tagged(&naming()));
+        assert!(!fixture("oo.tag.oo.123.png").is_tagged(&naming()));
+        assert!(!fixture("/path/to/some.dir/some.dir.0001.jpg").is_tagged(&naming()));
+        assert!(!fixture("/path/to/some.tag.dir/some.dir.0001.jpg").is_tagged(&naming()));
+        assert!(!fixture("some.dir.0001.jpg").is_tagged(&naming()));
+        assert!(!fixture("butagy_rabbit.jpg").is_tagged(&naming()));
+        assert!(fixture("some.dir/some.dir.tag.0001").is_tagged(&naming()));
+        assert!(fixture("some.dir.tag.0001.tar.gz").is_tagged(&naming()));
+        assert!(!fixture("some.dir.0001.tar.gz").is_tagged(&naming()));
     }
 }
@@ -1,40 +1,43 @@
 use crate::utils::dir;
+use crate::utils::extension::Naming;
+use crate::utils::sidecar;
 use crate::utils::types::FileTokens;
-use anyhow::{anyhow, Context};
+use anyhow::{Context, anyhow};
 use camino::{Utf8Path, Utf8PathBuf};
 use std::fs;
 
 impl FileTokens {
-    pub fn new(file: &Utf8Path, tag: &str) -> anyhow::Result<FileTokens> {
+    pub fn new(file: &Utf8Path, naming: &Naming) -> anyhow::Result<FileTokens> {
         let file = file.canonicalize_utf8()?;
 
         let basename = file.file_name().context("cannot get basename")?.to_string();
         let dirname = file.parent().context("cannot get dirname")?.to_path_buf();
 
-        let tokens: Vec<&str> = basename.split('.').collect();
+        let key = sidecar::item_key(&file, naming);
+        let tokens: Vec<&str> = key.split('.').collect();
         let token_count = tokens.len();
 
-        if token_count < 3 {
+        if token_count < 2 {
             return Err(anyhow!("too few file tokens",));
         }
 
-        let number = tokens[token_count - 2].parse::<i32>().ok();
-        let is_tagged = tag == tokens[token_count - 3];
+        let number = tokens[token_count - 1].parse::<i32>().ok();
+        let is_tagged = token_count > 2 && naming.tag == tokens[token_count - 2];
 
         let mtime = fs::metadata(&file)?.modified()?;
 
         let stem_token_count = if is_tagged {
-            token_count - 3
-        } else {
             token_count - 2
+        } else {
+            token_count - 1
         };
 
         Ok(FileTokens {
             dir: dirname,
             stem: tokens[..stem_token_count].join("."),
             num: number,
-            suffix: tokens[token_count - 1].to_string(),
-            tag: tag.to_string(),
+            suffix: basename[key.len()..].trim_start_matches('.').to_string(),
+            tag: naming.tag.clone(),
             is_tagged,
             mtime,
         })
@@ -46,7 +49,9 @@ impl FileTokens {
             bits.push(self.tag.clone());
         }
         bits.push(dir::pad_num(num));
-        bits.push(self.suffix.clone());
+        if !self.suffix.is_empty() {
+            bits.push(self.suffix.clone());
+        }
         self.dir.join(bits.join("."))
     }
 }
@@ -55,7 +60,7 @@ impl FileTokens {
 mod test {
     use super::*;
     use std::time::SystemTime;
-    use test_utils::fixture;
+    use test_utils::{fixture, fixture_dir};
 
     // Custom PartialEq for the tests. We don't want to compare mtime, because on
     // a git checkout the real one could be anything.
@@ -82,7 +87,7 @@ mod test {
                 tag: "xxx".to_string(),
                 mtime: SystemTime::now(), // we don't compare this
             },
-            FileTokens::new(&fixture("some.dir/some.dir.0002.jpg"), "xxx").unwrap(),
+            FileTokens::new(&fixture("some.dir/some.dir.0002.jpg"), &Naming::new("xxx")).unwrap(),
         );
 
         assert_eq!(
@@ -95,7 +100,11 @@ mod test {
                 tag: "tag".to_string(),
                 mtime: SystemTime::now(),
             },
-            FileTokens::new(&fixture("some.dir/some.dir.tag.0002.jpg"), "tag").unwrap(),
+            FileTokens::new(
+                &fixture("some.dir/some.dir.tag.0002.jpg"),
+                &Naming::new("tag")
+            )
+            .unwrap(),
         );
 
         assert_eq!(
@@ -108,9 +117,50 @@ mod test {
                 tag: "xxx".to_string(),
                 mtime: SystemTime::now(),
             },
-            FileTokens::new(&fixture("nodot/nodot.1234.sfx"), "xxx").unwrap(),
+            FileTokens::new(&fixture("nodot/nodot.1234.sfx"), &Naming::new("xxx")).unwrap(),
+        );
+
+        assert!(
+            FileTokens::new(&fixture("some.dir/random_name.jpg"), &Naming::new("tag")).is_err()
         );
+    }
+
+    #[test]
+    fn test_file_tokens_round_trip() {
+        let names = vec![
+            "rt.dir.0001.jpg",
+            "rt.dir.0002",
+            "rt.dir.0003.tar.gz",
+            "rt.dir.tag.0004",
+            "rt.dir.tag.0005.tar.bz2",
+            "rt.dir.0006.jpg.xmp",
+            "rt.dir.0007.001",
+            "rt.dir.tag.0008.001",
+        ];
+
+        let (_tmp, dir) = fixture_dir("rt.dir", names.clone());
 
-        assert!(FileTokens::new(&fixture("some.dir/random_name.jpg"), "tag").is_err());
+        for name in names {
+            let file = dir.join(name);
+            let tokens = FileTokens::new(&file, &Naming::new("tag")).unwrap();
+
+            assert_eq!("rt.dir", tokens.stem);
+            assert_eq!(file, tokens.make_filename_with_num(tokens.num.unwrap()));
+        }
+
+        let tokens = FileTokens::new(&dir.join("rt.dir.0003.tar.gz"), &Naming::new("tag")).unwrap();
+        assert_eq!("tar.gz", tokens.suffix);
+        assert_eq!(
+            dir.join("rt.dir.0009.tar.gz"),
+            tokens.make_filename_with_num(9)
+        );
+
+        let tokens = FileTokens::new(&dir.join("rt.dir.0007.001"), &Naming::new("tag")).unwrap();
+        assert_eq!(Some(7), tokens.num);
+        assert_eq!("001", tokens.suffix);
+        assert_eq!(
+            dir.join("rt.dir.0009.001"),
+            tokens.make_filename_with_num(9)
+        );
     }
 }
@@ -0,0 +1,281 @@
+use crate::error::Error;
+use crate::utils::types::RenameActions;
+use anyhow::{Context, anyhow};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+
+// Before a list of renames is executed, it is written to a journal in the
+// directory being worked on. Each completed rename is then recorded, so that
+// an interrupted run (which may leave files with temporary names) can be
+// completed or rolled back by `fseq dir recover`. The journal is removed when
+// the run finishes.
+//
+// The format is line-based and tab-separated:
+//
+//   mv <TAB> source <TAB> destination   (one per planned rename, in order)
+//   ok <TAB> index                      (one per completed rename)
+
+pub const JOURNAL_NAME: &str = ".fseq.journal";
+
+#[derive(Debug)]
+pub struct Journal {
+    pub path: Utf8PathBuf,
+    pub actions: RenameActions,
+    pub done: usize,
+    handle: Option<File>,
+}
+
+pub fn journal_path(dir: &Utf8Path) -> Utf8PathBuf {
+    dir.join(JOURNAL_NAME)
+}
+
+// Whether a line could be an ok record cut off part-way through.
+fn is_partial_ok(line: &str) -> bool {
+    match line.strip_prefix("ok\t") {
+        Some(index) => index.chars().all(|c| c.is_ascii_digit()),
+        None => "ok\t".starts_with(line),
+    }
+}
+
+pub fn is_journal(file: &Utf8Path) -> bool {
+    file.file_name()
+        .is_some_and(|name| name.starts_with(JOURNAL_NAME))
+}
+
+impl Journal {
+    // Writes a new journal for the given actions. Refuses to overwrite an
+    // existing one, because that belongs to an unfinished run.
+    pub fn create(dir: &Utf8Path, actions: &RenameActions) -> anyhow::Result<Journal> {
+        let path = journal_path(dir);
+
+        if path.exists() {
+            return Err(Error::Interrupted(dir.to_path_buf()).into());
+        }
+
+        Self::write(path, actions)
+    }
+
+    // Atomically replaces any existing journal with one for the given actions.
+    pub fn replace(dir: &Utf8Path, actions: &RenameActions) -> anyhow::Result<Journal> {
+        let path = journal_path(dir);
+        let tmp_path = dir.join(format!("{}.new", JOURNAL_NAME));
+
+        if tmp_path.exists() {
+            fs::remove_file(&tmp_path)?;
+        }
+
+        let mut journal = Self::write(tmp_path.clone(), actions)?;
+        fs::rename(&tmp_path, &path)?;
+        journal.path = path;
+        Ok(journal)
+    }
+
+    pub fn load(dir: &Utf8Path) -> anyhow::Result<Option<Journal>> {
+        let path = journal_path(dir);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let mut actions: RenameActions = Vec::new();
+        let mut done = 0;
+
+        let contents = fs::read_to_string(&path)?;
+        let lines: Vec<&str> = contents.lines().collect();
+
+        for (i, line) in lines.iter().enumerate() {
+            // A crash while appending can leave a partial last line, which has
+            // no newline and is the start of an ok record.
+            if i == lines.len() - 1
+                && !contents.ends_with('\n')
+                && !actions.is_empty()
+                && is_partial_ok(line)
+            {
+                break;
+            }
+
+            let fields: Vec<&str> = line.split('\t').collect();
+
+            match fields.as_slice() {
+                ["mv", src, dest] => actions.push((src.into(), dest.into())),
+                ["ok", index] => {
+                    let index: usize = index.parse()?;
+                    if index != done {
+                        return Err(anyhow!("{} line {}: out of sequence", path, i + 1));
+                    }
+                    done += 1;
+                }
+                _ => return Err(anyhow!("{} line {}: cannot parse", path, i + 1)),
+            }
+        }
+
+        if done > actions.len() {
+            return Err(anyhow!("{} records more work than it planned", path));
+        }
+
+        Ok(Some(Journal {
+            path,
+            actions,
+            done,
+            handle: None,
+        }))
+    }
+
+    fn write(path: Utf8PathBuf, actions: &RenameActions) -> anyhow::Result<Journal> {
+        let mut body = String::new();
+
+        for (src, dest) in actions {
+            if [src, dest]
+                .iter()
+                .any(|p| p.as_str().contains(['\t', '\n']))
+            {
+                return Err(anyhow!("cannot journal {} -> {}", src, dest));
+            }
+            body.push_str(&format!("mv\t{}\t{}\n", src, dest));
+        }
+
+        let mut handle = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("cannot create {}", path))?;
+
+        handle.write_all(body.as_bytes())?;
+        handle.sync_all()?;
+
+        Ok(Journal {
+            path,
+            actions: actions.clone(),
+            done: 0,
+            handle: Some(handle),
+        })
+    }
+
+    // Records that the action at the given index has been carried out.
+    pub fn record(&mut self, index: usize) -> anyhow::Result<()> {
+        if self.handle.is_none() {
+            self.handle = Some(OpenOptions::new().append(true).open(&self.path)?);
+        }
+
+        if let Some(handle) = self.handle.as_mut() {
+            // One write per record, so a crash cannot split it across lines.
+            handle.write_all(format!("ok\t{}\n", index).as_bytes())?;
+            handle.sync_data()?;
+        }
+
+        self.done = index + 1;
+        Ok(())
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        Ok(fs::remove_file(&self.path)?)
+    }
+
+    // The run may have been interrupted between a rename and its being
+    // recorded. If the next action's source is gone and its destination is
+    // present, it was carried out.
+    fn completed(&self) -> usize {
+        match self.actions.get(self.done) {
+            Some((src, dest)) if !src.exists() && dest.exists() => self.done + 1,
+            _ => self.done,
+        }
+    }
+
+    // The actions needed to finish the interrupted run.
+    pub fn remaining(&self) -> RenameActions {
+        self.actions[self.completed()..].to_vec()
+    }
+
+    // The actions needed to put everything back where it was.
+    pub fn rollback(&self) -> RenameActions {
+        self.actions[..self.completed()]
+            .iter()
+            .rev()
+            .map(|(src, dest)| (dest.clone(), src.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_utils::fixture_dir;
+
+    fn swap_actions(dir: &Utf8Path) -> RenameActions {
+        vec![
+            (dir.join("j.dir.0003.jpg"), dir.join("_j.dir.0002.jpg")),
+            (dir.join("j.dir.0002.jpg"), dir.join("j.dir.0003.jpg")),
+            (dir.join("_j.dir.0002.jpg"), dir.join("j.dir.0002.jpg")),
+        ]
+    }
+
+    #[test]
+    fn test_journal_round_trip() {
+        let (_tmp, dir) = fixture_dir("j.dir", vec!["j.dir.0002.jpg", "j.dir.0003.jpg"]);
+        let actions = swap_actions(&dir);
+        let mut journal = Journal::create(&dir, &actions).unwrap();
+
+        assert!(Journal::create(&dir, &actions).is_err());
+
+        journal.record(0).unwrap();
+        let loaded = Journal::load(&dir).unwrap().unwrap();
+        assert_eq!(actions, loaded.actions);
+        assert_eq!(1, loaded.done);
+
+        journal.finish().unwrap();
+        assert!(Journal::load(&dir).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_journal_remaining_and_rollback() {
+        let (_tmp, dir) = fixture_dir("j.dir", vec!["j.dir.0002.jpg", "j.dir.0003.jpg"]);
+        let actions = swap_actions(&dir);
+        let mut journal = Journal::create(&dir, &actions).unwrap();
+
+        // Simulate a crash after the first rename was recorded, and the second
+        // was carried out but not recorded.
+        fs::rename(&actions[0].0, &actions[0].1).unwrap();
+        journal.record(0).unwrap();
+        fs::rename(&actions[1].0, &actions[1].1).unwrap();
+
+        let loaded = Journal::load(&dir).unwrap().unwrap();
+
+        assert_eq!(vec![actions[2].clone()], loaded.remaining());
+
+        assert_eq!(
+            vec![
+                (dir.join("j.dir.0003.jpg"), dir.join("j.dir.0002.jpg")),
+                (dir.join("_j.dir.0002.jpg"), dir.join("j.dir.0003.jpg")),
+            ],
+            loaded.rollback()
+        );
+    }
+
+    #[test]
+    fn test_journal_load_truncated_record() {
+        let (_tmp, dir) = fixture_dir("j.dir", vec!["j.dir.0002.jpg", "j.dir.0003.jpg"]);
+        let actions = swap_actions(&dir);
+        let mut journal = Journal::create(&dir, &actions).unwrap();
+        let path = journal.path.clone();
+
+        for partial in ["o", "ok", "ok\t"] {
+            let mut handle = OpenOptions::new().append(true).open(&path).unwrap();
+            let whole = fs::read_to_string(&path).unwrap();
+            handle.write_all(partial.as_bytes()).unwrap();
+            assert_eq!(0, Journal::load(&dir).unwrap().unwrap().done);
+            fs::write(&path, whole).unwrap();
+        }
+
+        journal.record(0).unwrap();
+        journal.record(1).unwrap();
+
+        let mut handle = OpenOptions::new().append(true).open(&path).unwrap();
+        handle.write_all(b"ok\t2").unwrap();
+        assert_eq!(2, Journal::load(&dir).unwrap().unwrap().done);
+
+        // A complete line which cannot be parsed is still an error.
+        handle.write_all(b"x\n").unwrap();
+        assert!(Journal::load(&dir).is_err());
+    }
+}
@@ -1,6 +1,9 @@
 pub mod common;
 pub mod dir;
+pub mod extension;
 pub mod file;
 pub mod file_tokens;
-pub mod macros;
+pub mod journal;
+pub mod move_list;
+pub mod sidecar;
 pub mod types;
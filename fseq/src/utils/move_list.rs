@@ -0,0 +1,205 @@
+use crate::utils::extension::Naming;
+use crate::utils::sidecar;
+use crate::utils::types::{RenameActionWithIndex, RenameActions, RenameActionsResult};
+use anyhow::Context;
+use camino::{Utf8Path, Utf8PathBuf};
+
+// Returns the index and the value of the tuple in the inputs vec whose first
+// (source) element is idx. (The dest from a previous move.) Files are compared
+// as items, so a move onto x.0001.jpg links to a move of x.0001.cr2.
+fn find_next_link(
+    inputs: &RenameActions,
+    to_find: &Utf8PathBuf,
+    naming: &Naming,
+) -> RenameActionWithIndex {
+    inputs
+        .iter()
+        .enumerate()
+        .find(|(_index, (from, _to))| sidecar::same_item(from, to_find, naming))
+        .map(|(index, (from, to))| (index, (from.clone(), to.clone())))
+}
+
+fn tmp_name(original_name: &Utf8Path) -> anyhow::Result<Utf8PathBuf> {
+    let dir = original_name.parent().context("cannot make temp name")?;
+    let basename = original_name.file_name().context("file name missing")?;
+    Ok(dir.join(format!("_{}", basename)))
+}
+
+// Puts a list of renames into an order in which they can safely be carried
+// out, using temporary names to break any cycles.
+pub fn make_move_list(mut input: RenameActions, naming: &Naming) -> RenameActionsResult {
+    let mut ret: RenameActions = Vec::new();
+
+    while !input.is_empty() {
+        let (mut src, dest) = input.remove(0).clone();
+
+        match find_next_link(&input, &dest, naming) {
+            Some(_) => {
+                let tmpname = tmp_name(&dest)?;
+                ret.push((src.clone(), tmpname.clone()));
+                input.push((tmpname, dest));
+            }
+            None => {
+                ret.push((src.clone(), dest));
+            }
+        }
+
+        while let Some((index, (next_src, next_dest))) = find_next_link(&input, &src, naming) {
+            src = next_dest.clone();
+            ret.push((next_src, next_dest));
+            input.remove(index);
+        }
+    }
+
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_utils::fixture;
+
+    fn naming() -> Naming {
+        Naming::new("tag")
+    }
+
+    #[test]
+    fn test_find_next_link() {
+        let inputs = &vec![
+            (
+                fixture("age.dir/age.dir.0002.jpg"),
+                fixture("age.dir/age.dir.0001.jpg"),
+            ),
+            (
+                fixture("age.dir/age.dir.0003.jpg"),
+                fixture("age.dir/age.dir.0004.jpg"),
+            ),
+        ];
+
+        assert_eq!(
+            None,
+            find_next_link(inputs, &fixture("age.dir/age.dir.1234.jpg"), &naming())
+        );
+
+        assert_eq!(
+            Some((
+                0,
+                (
+                    fixture("age.dir/age.dir.0002.jpg"),
+                    fixture("age.dir/age.dir.0001.jpg"),
+                )
+            )),
+            find_next_link(inputs, &fixture("age.dir/age.dir.0002.jpg"), &naming())
+        );
+    }
+
+    #[test]
+    fn test_make_move_list() {
+        // Nothing to do.
+        let empty_vec: RenameActions = vec![];
+        assert_eq!(
+            empty_vec.clone(),
+            make_move_list(empty_vec, &naming()).unwrap()
+        );
+
+        // One move, to an empty slot.
+        assert_eq!(
+            vec![(
+                fixture("age.dir/age.dir.0004.jpg"),
+                fixture("age.dir/age.dir.0003.jpg"),
+            )],
+            make_move_list(
+                vec![(
+                    fixture("age.dir/age.dir.0004.jpg"),
+                    fixture("age.dir/age.dir.0003.jpg"),
+                )],
+                &naming()
+            )
+            .unwrap()
+        );
+
+        // Two to swap. This needs a temp file.
+        assert_eq!(
+            vec![
+                (
+                    fixture("age.dir/age.dir.0003.jpg"),
+                    fixture("age.dir/_age.dir.0002.jpg"),
+                ),
+                (
+                    fixture("age.dir/age.dir.0002.jpg"),
+                    fixture("age.dir/age.dir.0003.jpg"),
+                ),
+                (
+                    fixture("age.dir/_age.dir.0002.jpg"),
+                    fixture("age.dir/age.dir.0002.jpg"),
+                ),
+            ],
+            make_move_list(
+                vec![
+                    (
+                        fixture("age.dir/age.dir.0003.jpg"),
+                        fixture("age.dir/age.dir.0002.jpg"),
+                    ),
+                    (
+                        fixture("age.dir/age.dir.0002.jpg"),
+                        fixture("age.dir/age.dir.0003.jpg"),
+                    ),
+                ],
+                &naming()
+            )
+            .unwrap()
+        );
+
+        // Reverse the order of four files
+        assert_eq!(
+            vec![
+                (
+                    fixture("age.dir/age.dir.0001.jpg"),
+                    fixture("age.dir/_age.dir.0004.jpg"),
+                ),
+                (
+                    fixture("age.dir/age.dir.0002.jpg"),
+                    fixture("age.dir/_age.dir.0003.jpg"),
+                ),
+                (
+                    fixture("age.dir/age.dir.0003.jpg"),
+                    fixture("age.dir/age.dir.0002.jpg"),
+                ),
+                (
+                    fixture("age.dir/age.dir.0004.jpg"),
+                    fixture("age.dir/age.dir.0001.jpg"),
+                ),
+                (
+                    fixture("age.dir/_age.dir.0004.jpg"),
+                    fixture("age.dir/age.dir.0004.jpg"),
+                ),
+                (
+                    fixture("age.dir/_age.dir.0003.jpg"),
+                    fixture("age.dir/age.dir.0003.jpg"),
+                )
+            ],
+            make_move_list(
+                vec![
+                    (
+                        fixture("age.dir/age.dir.0001.jpg"),
+                        fixture("age.dir/age.dir.0004.jpg"),
+                    ),
+                    (
+                        fixture("age.dir/age.dir.0002.jpg"),
+                        fixture("age.dir/age.dir.0003.jpg"),
+                    ),
+                    (
+                        fixture("age.dir/age.dir.0003.jpg"),
+                        fixture("age.dir/age.dir.0002.jpg"),
+                    ),
+                    (
+                        fixture("age.dir/age.dir.0004.jpg"),
+                        fixture("age.dir/age.dir.0001.jpg"),
+                    ),
+                ],
+                &naming()
+            )
+            .unwrap()
+        );
+    }
+}
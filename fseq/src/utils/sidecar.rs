@@ -0,0 +1,397 @@
+use crate::utils::extension::Naming;
+use crate::utils::types::RenameActions;
+use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::{HashMap, HashSet};
+
+// Files which share a stem and number, like x.0003.jpg, x.0003.cr2 and
+// x.0003.jpg.xmp, are one logical item. Only one of them (the "primary") is
+// numbered, and every rename of it is expanded to rename the others along with
+// it.
+//
+// A file's item key is its name without its extension. Some sidecar files
+// have their extension appended to the full name of the file they describe,
+// rather than replacing its extension, so those lose two extensions.
+//
+// Sidecars and RAW files are companions, which belong with some other file.
+// Anything else is a lead. An item with two leads, like x.0003.txt and
+// x.0003.md, is really two files which collide on a number, so each lead is
+// numbered separately, and the leads never drag each other along.
+
+pub const SIDECAR_EXTENSIONS: &[&str] = &["xmp", "pp3", "dop"];
+
+pub const COMPANION_EXTENSIONS: &[&str] = &[
+    "aae", "arw", "cr2", "cr3", "dng", "nef", "orf", "raf", "rw2",
+];
+
+// True for things like x.0003.jpg.xmp, but not for x.0003.xmp.
+pub fn is_appended_sidecar(file: &Utf8Path, naming: &Naming) -> bool {
+    let name = file.file_name().unwrap_or_default();
+
+    match name.rsplit_once('.') {
+        Some((rest, ext)) if SIDECAR_EXTENSIONS.contains(&ext.to_lowercase().as_str()) => {
+            let (stem, inner) = naming.split(rest, dir_name(file));
+            !stem.is_empty() && !inner.is_empty()
+        }
+        _ => false,
+    }
+}
+
+pub fn item_key<'a>(file: &'a Utf8Path, naming: &Naming) -> &'a str {
+    let name = file.file_name().unwrap_or_default();
+
+    let name = match name.rfind('.') {
+        Some(i) if is_appended_sidecar(file, naming) => &name[..i],
+        _ => name,
+    };
+
+    naming.split(name, dir_name(file)).0
+}
+
+// Everything after the item key, like ".jpg" or ".jpg.xmp", or nothing at all.
+pub fn item_extension<'a>(file: &'a Utf8Path, naming: &Naming) -> &'a str {
+    let name = file.file_name().unwrap_or_default();
+    &name[item_key(file, naming).len()..]
+}
+
+fn dir_name(file: &Utf8Path) -> Option<&str> {
+    file.parent().and_then(|dir| dir.file_name())
+}
+
+// Whether an item extension, like ".cr2" or ".jpg.xmp", belongs to a companion.
+fn is_companion(rest: &str) -> bool {
+    let ext = rest.rsplit('.').next().unwrap_or_default().to_lowercase();
+    SIDECAR_EXTENSIONS.contains(&ext.as_str()) || COMPANION_EXTENSIONS.contains(&ext.as_str())
+}
+
+// Whether more than one of an item's members is a lead.
+fn has_collision<'a>(rests: impl IntoIterator<Item = &'a str>) -> bool {
+    rests.into_iter().filter(|rest| !is_companion(rest)).count() > 1
+}
+
+// Where a file's item lives: its directory and its key.
+fn item_path(file: &Utf8Path, naming: &Naming) -> Option<(Utf8PathBuf, String)> {
+    file.file_name()?;
+
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_str().is_empty() => dir.to_path_buf(),
+        _ => Utf8PathBuf::from("."),
+    };
+
+    Some((dir, item_key(file, naming).to_string()))
+}
+
+pub fn same_item(a: &Utf8Path, b: &Utf8Path, naming: &Naming) -> bool {
+    item_path(a, naming) == item_path(b, naming)
+}
+
+// Picks the file which represents each item in a list of files. Appended
+// sidecars are only chosen if there is nothing else. Where leads collide, each
+// of them is kept.
+pub fn primaries(mut files: Vec<Utf8PathBuf>, naming: &Naming) -> Vec<Utf8PathBuf> {
+    files.sort_by_key(|f| {
+        (
+            item_path(f, naming),
+            is_appended_sidecar(f, naming),
+            f.clone(),
+        )
+    });
+
+    let mut rests: HashMap<Option<(Utf8PathBuf, String)>, Vec<&str>> = HashMap::new();
+
+    for f in files.iter() {
+        rests
+            .entry(item_path(f, naming))
+            .or_default()
+            .push(item_extension(f, naming));
+    }
+
+    let colliding: HashSet<Option<(Utf8PathBuf, String)>> = rests
+        .into_iter()
+        .filter(|(_item, rests)| has_collision(rests.iter().copied()))
+        .map(|(item, _rests)| item)
+        .collect();
+
+    let mut seen: HashSet<Option<(Utf8PathBuf, String)>> = HashSet::new();
+
+    files.retain(|f| {
+        let item = item_path(f, naming);
+
+        if colliding.contains(&item) {
+            !is_companion(item_extension(f, naming))
+        } else {
+            seen.insert(item)
+        }
+    });
+
+    files.sort();
+    files
+}
+
+// Adds renames for the other members of each renamed file's item. Because
+// actions may use temporary names, we track where each item's members are as
+// we go, rather than only looking on disk.
+pub fn with_sidecars(actions: RenameActions, naming: &Naming) -> RenameActions {
+    let sources: HashSet<&Utf8PathBuf> = actions.iter().map(|(src, _dest)| src).collect();
+    let mut members: HashMap<(Utf8PathBuf, String), Vec<String>> = HashMap::new();
+    let mut listed: HashSet<Utf8PathBuf> = HashSet::new();
+    let mut ret: RenameActions = Vec::with_capacity(actions.len());
+
+    for (src, dest) in actions.iter() {
+        ret.push((src.clone(), dest.clone()));
+
+        let (Some(src_item), Some(dest_item)) = (item_path(src, naming), item_path(dest, naming))
+        else {
+            continue;
+        };
+
+        for dir in [&src_item.0, &dest_item.0] {
+            if listed.insert(dir.clone()) {
+                list_members(dir, &mut members, naming);
+            }
+        }
+
+        let src_rest = item_extension(src, naming).to_string();
+        let dest_rest = item_extension(dest, naming).to_string();
+
+        let present = members.remove(&src_item).unwrap_or_default();
+        let collision = has_collision(present.iter().map(String::as_str));
+
+        let (staying, mut moving): (Vec<String>, Vec<String>) = present
+            .into_iter()
+            .filter(|rest| *rest != src_rest)
+            .partition(|rest| {
+                (collision && !is_companion(rest))
+                    || sources.contains(&src_item.0.join(format!("{}{}", src_item.1, rest)))
+            });
+
+        moving.sort();
+
+        for rest in moving.iter() {
+            ret.push((
+                src_item.0.join(format!("{}{}", src_item.1, rest)),
+                dest_item.0.join(format!("{}{}", dest_item.1, rest)),
+            ));
+        }
+
+        if !staying.is_empty() {
+            members.insert(src_item, staying);
+        }
+
+        let arrived = members.entry(dest_item).or_default();
+        arrived.push(dest_rest);
+        arrived.extend(moving);
+    }
+
+    ret
+}
+
+fn list_members(
+    dir: &Utf8Path,
+    members: &mut HashMap<(Utf8PathBuf, String), Vec<String>>,
+    naming: &Naming,
+) {
+    if let Ok(entries) = dir.read_dir_utf8() {
+        for entry in entries.filter_map(Result::ok) {
+            if entry.path().is_dir() {
+                continue;
+            }
+
+            let path = dir.join(entry.file_name());
+
+            members
+                .entry((dir.to_path_buf(), item_key(&path, naming).to_string()))
+                .or_default()
+                .push(item_extension(&path, naming).to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_utils::{fixture, fixture_dir};
+
+    fn naming() -> Naming {
+        Naming::new("tag")
+    }
+
+    fn key(file: &str) -> &str {
+        item_key(Utf8Path::new(file), &naming())
+    }
+
+    #[test]
+    fn test_is_appended_sidecar() {
+        let appended = |file: &str| is_appended_sidecar(Utf8Path::new(file), &naming());
+
+        assert!(appended("x/x.0003.jpg.xmp"));
+        assert!(appended("x/IMG_1234.CR2.XMP"));
+        assert!(!appended("x/x.0003.xmp"));
+        assert!(!appended("x/x.xmp"));
+        assert!(!appended("x/x.0003.jpg"));
+    }
+
+    #[test]
+    fn test_item_key() {
+        assert_eq!("x.0003", key("x/x.0003.jpg"));
+        assert_eq!("x.0003", key("x/x.0003.cr2"));
+        assert_eq!("x.0003", key("x/x.0003.jpg.xmp"));
+        assert_eq!("x.0003", key("x/x.0003.xmp"));
+        assert_eq!("x.tag.0003", key("x/x.tag.0003.jpg"));
+        assert_eq!("IMG_1234", key("x/IMG_1234.jpg"));
+        assert_eq!("no_extension", key("x/no_extension"));
+        assert_eq!("x.0003", key("x/x.0003"));
+        assert_eq!("x.tag.0003", key("x/x.tag.0003"));
+        assert_eq!("x.0003", key("x/x.0003.tar.gz"));
+        assert_eq!("x.0003", key("x/x.0003.tar.gz.xmp"));
+        assert_eq!(".hidden", key("x/.hidden"));
+    }
+
+    #[test]
+    fn test_item_key_numeric_extension() {
+        assert_eq!("x.0003", key("x/x.0003.001"));
+        assert_eq!("x.0003", key("x/x.0003.001.xmp"));
+        assert_eq!("archive.7z", key("x/archive.7z.001"));
+        assert_eq!("y", key("x/y.0003"));
+    }
+
+    #[test]
+    fn test_item_extension() {
+        let ext = |file: &str| item_extension(Utf8Path::new(file), &naming()).to_string();
+
+        assert_eq!(".jpg", ext("x/x.0003.jpg"));
+        assert_eq!(".jpg.xmp", ext("x/x.0003.jpg.xmp"));
+        assert_eq!("", ext("x/no_extension"));
+        assert_eq!("", ext("x/x.0003"));
+        assert_eq!(".tar.gz", ext("x/x.0003.tar.gz"));
+        assert_eq!(".001", ext("x/x.0003.001"));
+    }
+
+    #[test]
+    fn test_primaries() {
+        assert_eq!(
+            vec![
+                fixture("sidecar.dir/IMG_1234.cr2"),
+                fixture("sidecar.dir/orphan.jpg.xmp"),
+                fixture("sidecar.dir/sidecar.dir.0001.cr2"),
+                fixture("sidecar.dir/sidecar.dir.0003.jpg"),
+            ],
+            primaries(
+                vec![
+                    fixture("sidecar.dir/sidecar.dir.0001.jpg.xmp"),
+                    fixture("sidecar.dir/sidecar.dir.0001.jpg"),
+                    fixture("sidecar.dir/sidecar.dir.0001.cr2"),
+                    fixture("sidecar.dir/sidecar.dir.0003.jpg.xmp"),
+                    fixture("sidecar.dir/sidecar.dir.0003.jpg"),
+                    fixture("sidecar.dir/IMG_1234.jpg"),
+                    fixture("sidecar.dir/IMG_1234.cr2"),
+                    fixture("sidecar.dir/orphan.jpg.xmp"),
+                ],
+                &naming()
+            )
+        );
+    }
+
+    #[test]
+    fn test_primaries_with_collision() {
+        assert_eq!(
+            vec![
+                Utf8PathBuf::from("d/x.0003.md"),
+                Utf8PathBuf::from("d/x.0003.txt"),
+                Utf8PathBuf::from("d/y.0001.cr2"),
+            ],
+            primaries(
+                vec![
+                    Utf8PathBuf::from("d/x.0003.txt"),
+                    Utf8PathBuf::from("d/x.0003.txt.xmp"),
+                    Utf8PathBuf::from("d/x.0003.md"),
+                    Utf8PathBuf::from("d/x.0003.cr2"),
+                    Utf8PathBuf::from("d/y.0001.cr2"),
+                    Utf8PathBuf::from("d/y.0001.jpg"),
+                ],
+                &naming()
+            )
+        );
+    }
+
+    #[test]
+    fn test_with_sidecars_collision() {
+        let (_tmp, dir) = fixture_dir(
+            "x",
+            vec!["x.0003.txt", "x.0003.md", "x.0003.txt.xmp", "x.0003.cr2"],
+        );
+
+        assert_eq!(
+            vec![
+                (dir.join("x.0003.txt"), dir.join("x.0001.txt")),
+                (dir.join("x.0003.cr2"), dir.join("x.0001.cr2")),
+                (dir.join("x.0003.txt.xmp"), dir.join("x.0001.txt.xmp")),
+            ],
+            with_sidecars(
+                vec![(dir.join("x.0003.txt"), dir.join("x.0001.txt"))],
+                &naming()
+            )
+        );
+    }
+
+    #[test]
+    fn test_with_sidecars() {
+        assert!(with_sidecars(Vec::new(), &naming()).is_empty());
+
+        // A swap, using a temporary name.
+        let result = with_sidecars(
+            vec![
+                (
+                    fixture("sidecar.dir/sidecar.dir.0001.cr2"),
+                    fixture("sidecar.dir/_sidecar.dir.0003.cr2"),
+                ),
+                (
+                    fixture("sidecar.dir/sidecar.dir.0003.jpg"),
+                    fixture("sidecar.dir/sidecar.dir.0001.jpg"),
+                ),
+                (
+                    fixture("sidecar.dir/_sidecar.dir.0003.cr2"),
+                    fixture("sidecar.dir/sidecar.dir.0003.cr2"),
+                ),
+            ],
+            &naming(),
+        );
+
+        assert_eq!(
+            vec![
+                (
+                    fixture("sidecar.dir/sidecar.dir.0001.cr2"),
+                    fixture("sidecar.dir/_sidecar.dir.0003.cr2"),
+                ),
+                (
+                    fixture("sidecar.dir/sidecar.dir.0001.jpg"),
+                    fixture("sidecar.dir/_sidecar.dir.0003.jpg"),
+                ),
+                (
+                    fixture("sidecar.dir/sidecar.dir.0001.jpg.xmp"),
+                    fixture("sidecar.dir/_sidecar.dir.0003.jpg.xmp"),
+                ),
+                (
+                    fixture("sidecar.dir/sidecar.dir.0003.jpg"),
+                    fixture("sidecar.dir/sidecar.dir.0001.jpg"),
+                ),
+                (
+                    fixture("sidecar.dir/sidecar.dir.0003.jpg.xmp"),
+                    fixture("sidecar.dir/sidecar.dir.0001.jpg.xmp"),
+                ),
+                (
+                    fixture("sidecar.dir/_sidecar.dir.0003.cr2"),
+                    fixture("sidecar.dir/sidecar.dir.0003.cr2"),
+                ),
+                (
+                    fixture("sidecar.dir/_sidecar.dir.0003.jpg"),
+                    fixture("sidecar.dir/sidecar.dir.0003.jpg"),
+                ),
+                (
+                    fixture("sidecar.dir/_sidecar.dir.0003.jpg.xmp"),
+                    fixture("sidecar.dir/sidecar.dir.0003.jpg.xmp"),
+                ),
+            ],
+            result
+        );
+    }
+}
@@ -7,13 +7,6 @@ pub type RenameActionsResult = anyhow::Result<RenameActions>;
 pub type RenameActionWithIndex = Option<(usize, RenameAction)>;
 pub type PathAndTokens = (Utf8PathBuf, FileTokens);
 
-#[derive(Debug)]
-pub struct Opts {
-    pub noop: bool,
-    pub tag: String,
-    pub verbose: bool,
-}
-
 #[derive(Debug)]
 pub struct FileTokens {
     pub dir: Utf8PathBuf,
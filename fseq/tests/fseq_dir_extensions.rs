@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use test_utils::fixture_dir;
+
+    #[test]
+    #[ignore]
+    fn test_fseq_consolidate_extensions() {
+        let (_tmp, test_dir) = fixture_dir(
+            "fseq.test",
+            vec![
+                "fseq.test.0002",
+                "fseq.test.0004.tar.gz",
+                "fseq.test.0005.pkg.zip",
+                "backup.tar.gz",
+            ],
+        );
+
+        cargo_bin_cmd!("fseq")
+            .arg("--compound-exts=tar.gz,pkg.zip")
+            .arg("dir")
+            .arg("consolidate")
+            .arg(&test_dir)
+            .assert()
+            .success();
+
+        assert_eq!(4, test_dir.read_dir().unwrap().count());
+
+        for (file, was) in [
+            ("fseq.test.0001.pkg.zip", "fseq.test.0005.pkg.zip"),
+            ("fseq.test.0002", "fseq.test.0002"),
+            ("fseq.test.0003.tar.gz", "fseq.test.0004.tar.gz"),
+            ("fseq.test.0004.tar.gz", "backup.tar.gz"),
+        ] {
+            assert_eq!(was, std::fs::read_to_string(test_dir.join(file)).unwrap());
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_consolidate_numeric_extensions() {
+        let (_tmp, test_dir) = fixture_dir(
+            "fseq.test",
+            vec!["fseq.test.0001", "fseq.test.0003.001", "archive.7z.001"],
+        );
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("consolidate")
+            .arg(&test_dir)
+            .assert()
+            .success();
+
+        assert_eq!(3, test_dir.read_dir().unwrap().count());
+
+        for (file, was) in [
+            ("fseq.test.0001", "fseq.test.0001"),
+            ("fseq.test.0002.001", "fseq.test.0003.001"),
+            ("fseq.test.0003.001", "archive.7z.001"),
+        ] {
+            assert_eq!(was, std::fs::read_to_string(test_dir.join(file)).unwrap());
+        }
+    }
+}
@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino::Utf8Path;
+    use predicates::prelude::*;
+    use std::fs::{self, File};
+    use std::time::{Duration, SystemTime};
+    use test_utils::fixture_dir;
+
+    fn set_age(file: &Utf8Path, seconds_ago: u64) {
+        let mtime = SystemTime::now() - Duration::from_secs(seconds_ago);
+        File::options()
+            .write(true)
+            .open(file)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_merge() {
+        let (_src_tmp, src_dir) = fixture_dir(
+            "other.shoot",
+            vec![
+                "other.shoot.0001.txt",
+                "other.shoot.TAG.0001.txt",
+                "rogue.txt",
+            ],
+        );
+
+        let (_dest_tmp, dest_dir) = fixture_dir(
+            "fseq.test",
+            vec!["fseq.test.0001.txt", "fseq.test.0002.txt"],
+        );
+
+        cargo_bin_cmd!("fseq")
+            .arg("--tag=TAG")
+            .arg("dir")
+            .arg("merge")
+            .arg(&src_dir)
+            .arg(&dest_dir)
+            .assert()
+            .success();
+
+        assert!(!src_dir.exists());
+        assert_eq!(5, dest_dir.read_dir().unwrap().count());
+
+        for (file, original) in [
+            ("fseq.test.0001.txt", "fseq.test.0001.txt"),
+            ("fseq.test.0002.txt", "fseq.test.0002.txt"),
+            ("fseq.test.0003.txt", "other.shoot.0001.txt"),
+            ("fseq.test.0004.txt", "rogue.txt"),
+            ("fseq.test.TAG.0001.txt", "other.shoot.TAG.0001.txt"),
+        ] {
+            assert_eq!(original, fs::read_to_string(dest_dir.join(file)).unwrap());
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_merge_by_age() {
+        let (_src_tmp, src_dir) = fixture_dir(
+            "other.shoot",
+            vec!["other.shoot.0001.txt", "other.shoot.0002.txt"],
+        );
+
+        let (_dest_tmp, dest_dir) = fixture_dir(
+            "fseq.test",
+            vec!["fseq.test.0001.txt", "fseq.test.0002.txt"],
+        );
+
+        set_age(&dest_dir.join("fseq.test.0001.txt"), 400);
+        set_age(&src_dir.join("other.shoot.0001.txt"), 300);
+        set_age(&dest_dir.join("fseq.test.0002.txt"), 200);
+        set_age(&src_dir.join("other.shoot.0002.txt"), 100);
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("merge")
+            .arg("--by-age")
+            .arg(&src_dir)
+            .arg(&dest_dir)
+            .assert()
+            .success();
+
+        assert!(!src_dir.exists());
+        assert_eq!(4, dest_dir.read_dir().unwrap().count());
+
+        for (file, original) in [
+            ("fseq.test.0001.txt", "fseq.test.0001.txt"),
+            ("fseq.test.0002.txt", "other.shoot.0001.txt"),
+            ("fseq.test.0003.txt", "fseq.test.0002.txt"),
+            ("fseq.test.0004.txt", "other.shoot.0002.txt"),
+        ] {
+            assert_eq!(original, fs::read_to_string(dest_dir.join(file)).unwrap());
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_merge_into_itself() {
+        let (_tmp, test_dir) = fixture_dir("fseq.test", vec!["fseq.test.0001.txt"]);
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("merge")
+            .arg(&test_dir)
+            .arg(&test_dir)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("into itself"));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_merge_missing_dir() {
+        let (_tmp, test_dir) = fixture_dir("fseq.test", vec!["fseq.test.0001.txt"]);
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("merge")
+            .arg("/no/such/dir")
+            .arg(&test_dir)
+            .assert()
+            .failure()
+            .stderr("ERROR: No such file or directory (os error 2)\n");
+    }
+}
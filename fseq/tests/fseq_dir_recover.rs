@@ -0,0 +1,158 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino::Utf8Path;
+    use predicates::prelude::*;
+    use std::fs;
+    use test_utils::fixture_dir;
+
+    // Leaves the directory as it would be if a swap of 0002 and 0003 had been
+    // interrupted after the first rename.
+    fn interrupted_swap(dir: &Utf8Path) {
+        let journal = format!(
+            "mv\t{d}/fseq.test.0003.txt\t{d}/_fseq.test.0002.txt\n\
+             mv\t{d}/fseq.test.0002.txt\t{d}/fseq.test.0003.txt\n\
+             mv\t{d}/_fseq.test.0002.txt\t{d}/fseq.test.0002.txt\n\
+             ok\t0\n",
+            d = dir
+        );
+
+        fs::write(dir.join(".fseq.journal"), journal).unwrap();
+        fs::rename(
+            dir.join("fseq.test.0003.txt"),
+            dir.join("_fseq.test.0002.txt"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_recover_complete() {
+        let (_tmp, test_dir) = fixture_dir(
+            "fseq.test",
+            vec!["fseq.test.0002.txt", "fseq.test.0003.txt"],
+        );
+
+        interrupted_swap(&test_dir);
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("consolidate")
+            .arg(&test_dir)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("fseq dir recover"));
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("recover")
+            .arg(&test_dir)
+            .assert()
+            .success();
+
+        assert_eq!(2, test_dir.read_dir().unwrap().count());
+
+        assert_eq!(
+            "fseq.test.0003.txt",
+            fs::read_to_string(test_dir.join("fseq.test.0002.txt")).unwrap()
+        );
+
+        assert_eq!(
+            "fseq.test.0002.txt",
+            fs::read_to_string(test_dir.join("fseq.test.0003.txt")).unwrap()
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_recover_rollback() {
+        let (_tmp, test_dir) = fixture_dir(
+            "fseq.test",
+            vec!["fseq.test.0002.txt", "fseq.test.0003.txt"],
+        );
+
+        interrupted_swap(&test_dir);
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("recover")
+            .arg("--rollback")
+            .arg(&test_dir)
+            .assert()
+            .success();
+
+        assert_eq!(2, test_dir.read_dir().unwrap().count());
+
+        for file in ["fseq.test.0002.txt", "fseq.test.0003.txt"] {
+            assert_eq!(file, fs::read_to_string(test_dir.join(file)).unwrap());
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_recover_truncated_journal() {
+        let (_tmp, test_dir) = fixture_dir(
+            "fseq.test",
+            vec!["fseq.test.0002.txt", "fseq.test.0003.txt"],
+        );
+
+        interrupted_swap(&test_dir);
+
+        // The second rename happened, but its record was cut off.
+        let journal = test_dir.join(".fseq.journal");
+        let mut contents = fs::read_to_string(&journal).unwrap();
+        contents.push_str("ok\t");
+        fs::write(&journal, contents).unwrap();
+        fs::rename(
+            test_dir.join("fseq.test.0002.txt"),
+            test_dir.join("fseq.test.0003.txt"),
+        )
+        .unwrap();
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("recover")
+            .arg(&test_dir)
+            .assert()
+            .success();
+
+        assert_eq!(2, test_dir.read_dir().unwrap().count());
+
+        assert_eq!(
+            "fseq.test.0003.txt",
+            fs::read_to_string(test_dir.join("fseq.test.0002.txt")).unwrap()
+        );
+
+        assert_eq!(
+            "fseq.test.0002.txt",
+            fs::read_to_string(test_dir.join("fseq.test.0003.txt")).unwrap()
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_recover_nothing_to_do() {
+        let (_tmp, test_dir) = fixture_dir("fseq.test", vec!["fseq.test.0001.txt"]);
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("recover")
+            .arg(&test_dir)
+            .assert()
+            .success();
+
+        assert!(test_dir.join("fseq.test.0001.txt").exists());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_recover_missing_dir() {
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("recover")
+            .arg("/no/such/dir")
+            .assert()
+            .failure()
+            .stderr("ERROR: /no/such/dir is not a directory\n");
+    }
+}
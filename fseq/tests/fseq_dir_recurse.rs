@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+    use predicates::prelude::*;
+    use std::fs;
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_consolidate_recurse() {
+        let temp = Utf8TempDir::new().unwrap();
+        let root = temp.path().canonicalize_utf8().unwrap();
+
+        for file in [
+            "a.dir/rogue.txt",
+            "a.dir/a.dir.0003.txt",
+            "b.dir/c.dir/c.dir.0002.txt",
+            ".hidden/rogue.txt",
+            "loose.txt",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, file).unwrap();
+        }
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("consolidate")
+            .arg("-r")
+            .arg(&root)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(format!(
+                "{}/a.dir: 2 renamed",
+                root
+            )))
+            .stdout(predicate::str::contains(format!(
+                "{}/b.dir: 0 renamed",
+                root
+            )))
+            .stdout(predicate::str::contains(format!(
+                "{}/b.dir/c.dir: 1 renamed",
+                root
+            )))
+            .stdout(predicate::str::contains("3 directories, 0 errors"));
+
+        assert!(root.join("a.dir/a.dir.0001.txt").exists());
+        assert!(root.join("a.dir/a.dir.0002.txt").exists());
+        assert!(root.join("b.dir/c.dir/c.dir.0001.txt").exists());
+
+        // The root itself, and hidden directories, are left alone.
+        assert!(root.join("loose.txt").exists());
+        assert!(root.join(".hidden/rogue.txt").exists());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_status_recurse() {
+        let temp = Utf8TempDir::new().unwrap();
+        let root = temp.path().canonicalize_utf8().unwrap();
+        fs::create_dir_all(root.join("a.dir")).unwrap();
+        fs::write(root.join("a.dir/a.dir.0002.txt"), "").unwrap();
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("-r")
+            .arg("status")
+            .arg(&root)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(format!(
+                "{}/a.dir: needs consolidating",
+                root
+            )));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_recurse_missing_dir() {
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("num-by-age")
+            .arg("-r")
+            .arg("/no/such/dir")
+            .assert()
+            .failure();
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use std::fs::File;
+    use std::time::{Duration, SystemTime};
+    use test_utils::fixture_dir;
+
+    #[test]
+    #[ignore]
+    fn test_fseq_consolidate_with_sidecars() {
+        let (_tmp, test_dir) = fixture_dir(
+            "fseq.test",
+            vec![
+                "fseq.test.0001.jpg",
+                "fseq.test.0001.cr2",
+                "fseq.test.0004.jpg",
+                "fseq.test.0004.jpg.xmp",
+                "fseq.test.0004.cr2",
+                "IMG_1234.jpg",
+                "IMG_1234.aae",
+                "orphan.jpg.xmp",
+            ],
+        );
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("consolidate")
+            .arg(&test_dir)
+            .assert()
+            .success();
+
+        assert_eq!(8, test_dir.read_dir().unwrap().count());
+
+        for file in [
+            "fseq.test.0001.jpg",
+            "fseq.test.0001.cr2",
+            "fseq.test.0002.jpg",
+            "fseq.test.0002.jpg.xmp",
+            "fseq.test.0002.cr2",
+            "fseq.test.0003.jpg",
+            "fseq.test.0003.aae",
+            "fseq.test.0004.jpg.xmp",
+        ] {
+            assert!(test_dir.join(file).exists(), "{} missing", file);
+        }
+
+        // Members of an item keep their own contents.
+        assert_eq!(
+            "fseq.test.0004.jpg.xmp",
+            std::fs::read_to_string(test_dir.join("fseq.test.0002.jpg.xmp")).unwrap()
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_num_by_age_with_sidecars() {
+        let (_tmp, test_dir) = fixture_dir(
+            "fseq.test",
+            vec![
+                "fseq.test.0001.jpg",
+                "fseq.test.0001.cr2",
+                "fseq.test.0002.jpg",
+                "fseq.test.0002.jpg.xmp",
+            ],
+        );
+
+        let now = SystemTime::now();
+
+        for (file, age) in [
+            ("fseq.test.0001.jpg", 10),
+            ("fseq.test.0001.cr2", 10),
+            ("fseq.test.0002.jpg", 100),
+            ("fseq.test.0002.jpg.xmp", 1),
+        ] {
+            File::options()
+                .write(true)
+                .open(test_dir.join(file))
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age))
+                .unwrap();
+        }
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("num-by-age")
+            .arg(&test_dir)
+            .assert()
+            .success();
+
+        assert_eq!(4, test_dir.read_dir().unwrap().count());
+
+        for (file, was) in [
+            ("fseq.test.0001.jpg", "fseq.test.0002.jpg"),
+            ("fseq.test.0001.jpg.xmp", "fseq.test.0002.jpg.xmp"),
+            ("fseq.test.0002.jpg", "fseq.test.0001.jpg"),
+            ("fseq.test.0002.cr2", "fseq.test.0001.cr2"),
+        ] {
+            assert_eq!(was, std::fs::read_to_string(test_dir.join(file)).unwrap());
+        }
+    }
+}
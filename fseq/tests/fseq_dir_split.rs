@@ -0,0 +1,138 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino::Utf8Path;
+    use predicates::prelude::*;
+    use std::fs::{self, File};
+    use std::time::{Duration, SystemTime};
+    use test_utils::fixture_dir;
+
+    fn set_age(file: &Utf8Path, seconds_ago: u64) {
+        let mtime = SystemTime::now() - Duration::from_secs(seconds_ago);
+        File::options()
+            .write(true)
+            .open(file)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_split_every() {
+        let (_tmp, test_dir) = fixture_dir(
+            "fseq.test",
+            vec![
+                "fseq.test.0001.txt",
+                "fseq.test.0002.txt",
+                "fseq.test.0003.txt",
+            ],
+        );
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("split")
+            .arg("--every=2")
+            .arg(&test_dir)
+            .assert()
+            .success();
+
+        let parent = test_dir.parent().unwrap();
+
+        assert!(!test_dir.exists());
+
+        for (file, original) in [
+            ("fseq.test.1/fseq.test.1.0001.txt", "fseq.test.0001.txt"),
+            ("fseq.test.1/fseq.test.1.0002.txt", "fseq.test.0002.txt"),
+            ("fseq.test.2/fseq.test.2.0001.txt", "fseq.test.0003.txt"),
+        ] {
+            assert_eq!(original, fs::read_to_string(parent.join(file)).unwrap());
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_split_gap() {
+        let (_tmp, test_dir) = fixture_dir(
+            "fseq.test",
+            vec![
+                "fseq.test.0001.txt",
+                "fseq.test.0002.txt",
+                "fseq.test.0003.txt",
+            ],
+        );
+
+        set_age(&test_dir.join("fseq.test.0001.txt"), 86400);
+        set_age(&test_dir.join("fseq.test.0002.txt"), 600);
+        set_age(&test_dir.join("fseq.test.0003.txt"), 60);
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("split")
+            .arg("--gap=6h")
+            .arg("--pattern=event-{n}")
+            .arg(&test_dir)
+            .assert()
+            .success();
+
+        let parent = test_dir.parent().unwrap();
+
+        for (file, original) in [
+            ("event-1/event-1.0001.txt", "fseq.test.0001.txt"),
+            ("event-2/event-2.0001.txt", "fseq.test.0002.txt"),
+            ("event-2/event-2.0002.txt", "fseq.test.0003.txt"),
+        ] {
+            assert_eq!(original, fs::read_to_string(parent.join(file)).unwrap());
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_split_by_tag() {
+        let (_tmp, test_dir) = fixture_dir(
+            "fseq.test",
+            vec!["fseq.test.0001.txt", "fseq.test.TAG.0001.txt"],
+        );
+
+        cargo_bin_cmd!("fseq")
+            .arg("--tag=TAG")
+            .arg("dir")
+            .arg("split")
+            .arg("--by-tag")
+            .arg(&test_dir)
+            .assert()
+            .success();
+
+        let parent = test_dir.parent().unwrap();
+
+        assert!(parent.join("fseq.test.1/fseq.test.1.0001.txt").exists());
+        assert!(parent.join("fseq.test.2/fseq.test.2.TAG.0001.txt").exists());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_split_no_method() {
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("split")
+            .arg("/tmp")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "the following required arguments were not provided",
+            ));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_split_missing_dir() {
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("split")
+            .arg("--every=2")
+            .arg("/no/such/dir")
+            .assert()
+            .failure()
+            .stderr("ERROR: No such file or directory (os error 2)\n");
+    }
+}
@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use predicates::prelude::*;
+    use test_utils::fixture_dir;
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_status() {
+        let (_tmp, test_dir) = fixture_dir(
+            "fseq.test",
+            vec![
+                "fseq.test.0001.txt",
+                "fseq.test.0003.txt",
+                "fseq.test.0003.md",
+                "rogue.txt",
+                "fseq.test.TAG.0001.txt",
+            ],
+        );
+
+        cargo_bin_cmd!("fseq")
+            .arg("--tag=TAG")
+            .arg("dir")
+            .arg("status")
+            .arg(&test_dir)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(format!(
+                "{}: needs consolidating",
+                test_dir
+            )))
+            .stdout(predicate::str::contains(
+                "fseq.test: 3 numbered (1-3), 1 rogue, holes: 2, duplicates: 3",
+            ))
+            .stdout(predicate::str::contains(
+                "fseq.test.TAG: 1 numbered (1-1), 0 rogue, holes: none, duplicates: none",
+            ));
+
+        // Nothing should have changed.
+        assert_eq!(5, test_dir.read_dir().unwrap().count());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_status_json() {
+        let (_tmp, test_dir) = fixture_dir(
+            "fseq.test",
+            vec!["fseq.test.0001.txt", "fseq.test.0002.txt"],
+        );
+
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("status")
+            .arg("--json")
+            .arg(&test_dir)
+            .assert()
+            .success()
+            .stdout(predicate::str::starts_with("["))
+            .stdout(predicate::str::contains(r#""numbered": 2"#))
+            .stdout(predicate::str::contains(r#""holes": []"#))
+            .stdout(predicate::str::contains(r#""interrupted": false"#));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_dir_status_missing_dir() {
+        cargo_bin_cmd!("fseq")
+            .arg("dir")
+            .arg("status")
+            .arg("/no/such/dir")
+            .assert()
+            .failure()
+            .stderr("ERROR on /no/such/dir: No such file or directory (os error 2)\n");
+    }
+}
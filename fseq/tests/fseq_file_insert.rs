@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use predicates::prelude::*;
+    use std::fs;
+    use test_utils::fixture_dir;
+
+    #[test]
+    #[ignore]
+    fn test_fseq_file_insert() {
+        let (_tmp, test_dir) = fixture_dir(
+            "fseq.test",
+            vec![
+                "fseq.test.0001.txt",
+                "fseq.test.0002.txt",
+                "fseq.test.0003.txt",
+                "new.txt",
+            ],
+        );
+
+        cargo_bin_cmd!("fseq")
+            .arg("file")
+            .arg("insert")
+            .arg("--at=2")
+            .arg(test_dir.join("new.txt"))
+            .assert()
+            .success();
+
+        assert_eq!(4, test_dir.read_dir().unwrap().count());
+
+        for (file, original) in [
+            ("fseq.test.0001.txt", "fseq.test.0001.txt"),
+            ("fseq.test.0002.txt", "new.txt"),
+            ("fseq.test.0003.txt", "fseq.test.0002.txt"),
+            ("fseq.test.0004.txt", "fseq.test.0003.txt"),
+        ] {
+            assert_eq!(original, fs::read_to_string(test_dir.join(file)).unwrap());
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_file_insert_already_in_sequence() {
+        let (_tmp, test_dir) = fixture_dir("fseq.test", vec!["fseq.test.0001.txt"]);
+
+        cargo_bin_cmd!("fseq")
+            .arg("file")
+            .arg("insert")
+            .arg("--at=2")
+            .arg(test_dir.join("fseq.test.0001.txt"))
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("already in sequence"));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_file_insert_no_position() {
+        cargo_bin_cmd!("fseq")
+            .arg("file")
+            .arg("insert")
+            .arg("/no/such/file")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "the following required arguments were not provided",
+            ));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_file_insert_missing_file() {
+        cargo_bin_cmd!("fseq")
+            .arg("file")
+            .arg("insert")
+            .arg("--at=1")
+            .arg("/no/such/file")
+            .assert()
+            .failure()
+            .stderr("ERROR on /no/such/file: No such file or directory (os error 2)\n");
+    }
+}
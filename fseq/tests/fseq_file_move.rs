@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use predicates::prelude::*;
+    use std::fs;
+    use test_utils::fixture_dir;
+
+    #[test]
+    #[ignore]
+    fn test_fseq_file_move() {
+        let (_tmp, test_dir) = fixture_dir(
+            "fseq.test",
+            vec![
+                "fseq.test.0001.txt",
+                "fseq.test.0002.txt",
+                "fseq.test.0003.txt",
+                "fseq.test.0004.txt",
+            ],
+        );
+
+        cargo_bin_cmd!("fseq")
+            .arg("file")
+            .arg("move")
+            .arg(test_dir.join("fseq.test.0004.txt"))
+            .arg("--to=1")
+            .assert()
+            .success();
+
+        for (file, original) in [
+            ("fseq.test.0001.txt", "fseq.test.0004.txt"),
+            ("fseq.test.0002.txt", "fseq.test.0001.txt"),
+            ("fseq.test.0003.txt", "fseq.test.0002.txt"),
+            ("fseq.test.0004.txt", "fseq.test.0003.txt"),
+        ] {
+            assert_eq!(original, fs::read_to_string(test_dir.join(file)).unwrap());
+        }
+
+        cargo_bin_cmd!("fseq")
+            .arg("file")
+            .arg("move")
+            .arg(test_dir.join("fseq.test.0001.txt"))
+            .arg("--to=4")
+            .assert()
+            .success();
+
+        assert_eq!(4, test_dir.read_dir().unwrap().count());
+
+        for file in [
+            "fseq.test.0001.txt",
+            "fseq.test.0002.txt",
+            "fseq.test.0003.txt",
+            "fseq.test.0004.txt",
+        ] {
+            assert_eq!(file, fs::read_to_string(test_dir.join(file)).unwrap());
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_file_move_not_in_sequence() {
+        let (_tmp, test_dir) = fixture_dir("fseq.test", vec!["rogue.txt"]);
+
+        cargo_bin_cmd!("fseq")
+            .arg("file")
+            .arg("move")
+            .arg(test_dir.join("rogue.txt"))
+            .arg("--to=1")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("not in sequence"));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_fseq_file_move_missing_file() {
+        cargo_bin_cmd!("fseq")
+            .arg("file")
+            .arg("move")
+            .arg("/no/such/file")
+            .arg("--to=1")
+            .assert()
+            .failure()
+            .stderr("ERROR on /no/such/file: No such file or directory (os error 2)\n");
+    }
+}
@@ -0,0 +1,117 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use camino_tempfile_ext::prelude::*;
+use criterion::{Criterion, criterion_group, criterion_main};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use randos::utils::dir;
+use randos::utils::sample::{Balance, Sampler, Stratifier};
+use std::hint::black_box;
+
+// Picking a few files from a big tree: collecting every file then shuffling
+// them all, as randos used to, against streaming the tree through a reservoir
+// sample.
+
+const DIRS: usize = 200;
+const FILES_PER_DIR: usize = 100;
+const PICKS: usize = 10;
+
+fn tree() -> Utf8TempDir {
+    let root = Utf8TempDir::new().unwrap();
+
+    for d in 0..DIRS {
+        let dir = root.child(format!("dir_{}/sub_{}", d % 20, d));
+        dir.create_dir_all().unwrap();
+
+        for f in 0..FILES_PER_DIR {
+            dir.child(format!("file_{}.jpg", f)).touch().unwrap();
+        }
+    }
+
+    root
+}
+
+// The old way of listing a tree, kept here to measure against: gather every
+// directory, checking each against all those found so far, then every file in
+// them.
+fn old_expand_file_list(flist: &[Utf8PathBuf]) -> Vec<Utf8PathBuf> {
+    let mut ret: Vec<Utf8PathBuf> = Vec::new();
+    let mut dirs: Vec<Utf8PathBuf> = Vec::new();
+
+    for f in flist {
+        if f.is_file() {
+            ret.push(f.clone());
+        } else if f.is_dir() {
+            old_collect_directories(f, &mut dirs);
+        }
+    }
+
+    for dir in dirs {
+        if let Ok(entries) = dir.read_dir_utf8() {
+            for entry in entries.filter_map(Result::ok) {
+                let path = entry.into_path();
+                if path.is_file() {
+                    ret.push(path);
+                }
+            }
+        }
+    }
+
+    ret
+}
+
+fn old_collect_directories(dir: &Utf8Path, aggr: &mut Vec<Utf8PathBuf>) {
+    let dir_buf = dir.to_path_buf();
+
+    if !aggr.contains(&dir_buf) {
+        aggr.push(dir_buf);
+    }
+
+    if let Ok(entries) = dir.read_dir_utf8() {
+        for entry in entries.filter_map(Result::ok) {
+            if entry.path().is_dir() {
+                old_collect_directories(entry.path(), aggr);
+            }
+        }
+    }
+}
+
+fn collect_and_shuffle(roots: &[Utf8PathBuf], rng: &mut StdRng) -> Vec<Utf8PathBuf> {
+    let mut pool = old_expand_file_list(roots);
+    pool.shuffle(rng);
+    pool.truncate(PICKS);
+    pool
+}
+
+fn reservoir(roots: &[Utf8PathBuf], rng: &mut StdRng) -> Vec<Utf8PathBuf> {
+    let sources: Vec<(Utf8PathBuf, f64)> = roots.iter().map(|r| (r.clone(), 1.0)).collect();
+    let mut stratifier = Stratifier::new(Balance::Files, &sources, 30, Some(PICKS));
+
+    dir::walk_files(roots, true, None, |file| stratifier.offer(file, rng));
+
+    let mut sampler = Sampler::new(stratifier.into_strata(), rng);
+    std::iter::from_fn(|| sampler.next_accepted(|_| true)).collect()
+}
+
+fn bench_sampling(c: &mut Criterion) {
+    let root = tree();
+    let roots = vec![root.path().to_path_buf()];
+    let mut group = c.benchmark_group(format!("pick {} of {}", PICKS, DIRS * FILES_PER_DIR));
+
+    group.sample_size(20);
+
+    group.bench_function("collect and shuffle", |b| {
+        let mut rng = StdRng::seed_from_u64(1);
+        b.iter(|| black_box(collect_and_shuffle(&roots, &mut rng)))
+    });
+
+    group.bench_function("reservoir", |b| {
+        let mut rng = StdRng::seed_from_u64(1);
+        b.iter(|| black_box(reservoir(&roots, &mut rng)))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_sampling);
+criterion_main!(benches);
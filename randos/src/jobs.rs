@@ -0,0 +1,94 @@
+use camino::Utf8PathBuf;
+use randos::utils::actions;
+use randos::utils::progress::Progress;
+use randos::utils::types::ActionOpts;
+use std::io;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+// Carrying out a run's picks on --jobs workers, with a progress bar if it's
+// wanted.
+
+// A pick's source and destination, and the source's size and mtime, taken
+// before anything happens to it.
+pub type Job = (Utf8PathBuf, Utf8PathBuf, u64, Option<u64>);
+
+// Workers take the next job as they finish the last one, and say how it went.
+// Everything else is done here, by handing each job and its result to
+// finished, as they come in. Links take no time, so then the progress is in
+// files, not bytes.
+pub fn run(
+    jobs: &[Job],
+    opts: &ActionOpts,
+    workers: u64,
+    progress: bool,
+    mut finished: impl FnMut(&Job, io::Result<()>),
+) {
+    let progress = progress.then(|| {
+        let bytes = if opts.action.handler().copies() {
+            jobs.iter().map(|(_, _, size, _)| size).sum()
+        } else {
+            0
+        };
+
+        Progress::new(jobs.len(), bytes)
+    });
+
+    let next_job = AtomicUsize::new(0);
+    let copied = AtomicU64::new(0);
+    let (tx, rx) = mpsc::channel();
+    let mut done = 0;
+
+    thread::scope(|scope| {
+        for _ in 0..workers.min(jobs.len() as u64) {
+            let tx = tx.clone();
+            let (next_job, copied) = (&next_job, &copied);
+
+            scope.spawn(move || {
+                loop {
+                    let i = next_job.fetch_add(1, Ordering::Relaxed);
+
+                    let Some((source, dest, _, _)) = jobs.get(i) else {
+                        break;
+                    };
+
+                    let result = actions::run_counted(source, dest, opts, copied);
+
+                    if tx.send((i, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        drop(tx);
+
+        loop {
+            let (i, result) = match rx.recv_timeout(Duration::from_millis(250)) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => {
+                    if let Some(progress) = &progress {
+                        progress.draw(done, copied.load(Ordering::Relaxed));
+                    }
+
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            done += 1;
+
+            if let Some(progress) = &progress {
+                progress.draw(done, copied.load(Ordering::Relaxed));
+            }
+
+            finished(&jobs[i], result);
+        }
+    });
+
+    if let Some(progress) = progress {
+        progress.finish(done, copied.load(Ordering::Relaxed));
+    }
+}
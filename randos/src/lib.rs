@@ -0,0 +1,5 @@
+// randos picks random files and links, copies or moves them somewhere. The
+// library holds the finding, filtering and sampling of files, and the randos
+// binary is a command-line front end to it.
+
+pub mod utils;
@@ -1,33 +1,122 @@
-mod utils;
-use crate::utils::types::{ActionOpts, FilterOpts};
-use crate::utils::{actions, dir, filter, namer};
-use camino::Utf8PathBuf;
-use clap::Parser;
-use rand::{rng, seq::SliceRandom};
+mod jobs;
+mod pick;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::builder::PossibleValue;
+use clap::{Parser, ValueEnum};
+use jobs::Job;
+use pick::Selection;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use randos::utils::actions::{Action, Verify};
+use randos::utils::content::{Algorithm, UniqueContent};
+use randos::utils::filter::FileType;
+use randos::utils::history::History;
+use randos::utils::namer::{Collision, InUse, Namer, Scheme};
+use randos::utils::record::{self, Record};
+use randos::utils::sample::{Balance, Stratifier};
+use randos::utils::sync::{self, Manifest};
+use randos::utils::template::Template;
+use randos::utils::types::{ActionOpts, FilterOpts};
+use randos::utils::{actions, budget, dir, filter};
 use regex::Regex;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::sync::LazyLock;
 use time::{Duration, OffsetDateTime};
 
+// What a run does: an action on each pick, or sync, which keeps the
+// destination topped up, using an action --via.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Act(Action),
+    Sync,
+}
+
+static MODES: LazyLock<Vec<Mode>> = LazyLock::new(|| {
+    Action::value_variants()
+        .iter()
+        .map(|action| Mode::Act(*action))
+        .chain([Mode::Sync])
+        .collect()
+});
+
+impl ValueEnum for Mode {
+    fn value_variants<'a>() -> &'a [Self] {
+        &MODES
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        match self {
+            Mode::Act(action) => action.to_possible_value(),
+            Mode::Sync => Some(
+                PossibleValue::new("sync")
+                    .help("Make the destination hold exactly count files, linked or copied --via"),
+            ),
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
 #[clap(version, about = "Links to (semi-) random files", long_about = None)]
+#[clap(after_help = "To put back a run made with --record, use randos undo <record>")]
 struct Cli {
     /// Only consider source files with these extensions (comma separated)
     #[clap(short, long)]
     ext_list: Option<String>,
-    /// Only consider source files older than this many days
+    /// Only consider source files older than this. A number of days, or a number
+    /// with a unit: s, m, h, d, w or y, like 6h or 2w
     #[clap(short = 'O', long)]
-    older: Option<u16>,
-    /// Only consider source files newer than this many days
+    older: Option<String>,
+    /// Only consider source files newer than this. Takes the same units as --older
     #[clap(short = 'N', long)]
-    newer: Option<u16>,
+    newer: Option<String>,
     /// Only consider source files matching this Rust regex
     #[clap(short = 'x', long)]
     regex: Option<String>,
+    /// Ignore source files matching this Rust regex. May be repeated
+    #[clap(long)]
+    exclude: Vec<String>,
+    /// Ignore source files matching this glob, like '*.tmp'. A glob with a / in it
+    /// is matched against the whole path. May be repeated
+    #[clap(long)]
+    exclude_glob: Vec<String>,
+    /// Match --regex and --exclude against the whole path, not just the file name
+    #[clap(long)]
+    full_path: bool,
+    /// Only consider source files at least this big, like 100K or 2MB
+    #[clap(long)]
+    min_size: Option<String>,
+    /// Only consider source files at most this big
+    #[clap(long)]
+    max_size: Option<String>,
+    /// Only consider source files of these types, going by their contents
+    /// (comma separated)
+    #[clap(long = "type", value_enum, value_delimiter = ',')]
+    types: Vec<FileType>,
+    /// Don't recurse further than this. Files directly in a source are at depth 1.
+    /// Implies --recurse
+    #[clap(long, value_parser = clap::value_parser!(u64).range(1..))]
+    max_depth: Option<u64>,
     /// Use relative paths
     #[clap(short = 'R', long)]
     relative: bool,
-    /// How to name targets. One of plain, hash, sequential, expand. Defaults to plain
-    #[clap(short, long)]
-    scheme: Option<String>,
+    /// How to name targets
+    #[clap(short, long, value_enum, default_value_t = Scheme::Plain)]
+    scheme: Scheme,
+    /// Name targets from a template, like '{mtime:%Y%m%d}-{seq:05}-{name}'. See the
+    /// README for the placeholders
+    #[clap(short = 'T', long, conflicts_with = "scheme")]
+    template: Option<String>,
+    /// What to do when a name is already taken in the destination, and the scheme
+    /// can't give another
+    #[clap(long, value_enum, default_value_t = Collision::Suffix)]
+    collisions: Collision,
+    /// Put each file in the same place under the destination as it is under its
+    /// source directory, making directories as needed
+    #[clap(long)]
+    preserve_tree: bool,
     /// Recurse down source directories
     #[clap(short, long)]
     recurse: bool,
@@ -37,18 +126,80 @@ struct Cli {
     /// Say what would happen without doing it
     #[clap(short, long)]
     noop: bool,
-    /// Action to perform: mv (move), cp (copy), ln (symlink), lnh (hardlink), mvx (cross-fs move)
-    #[arg(required = true)]
-    action: String,
+    /// Seed the random number generator, so the same sources give the same picks
+    #[clap(long)]
+    seed: Option<u64>,
+    /// How to share picks out: evenly over files, or between sources, extensions or
+    /// age buckets. Defaults to sources if any source has a weight, otherwise files
+    #[clap(long, value_enum)]
+    balance: Option<Balance>,
+    /// Width of the age buckets for --balance age, in days
+    #[clap(long, default_value_t = 30)]
+    bucket_days: u64,
+    /// Record picked files in this history file
+    #[clap(long)]
+    history: Option<String>,
+    /// Don't pick files in the history until every candidate has been picked, then
+    /// start a new cycle
+    #[clap(long, requires = "history")]
+    no_repeat: bool,
+    /// Read more sources from this file, one per line, or from stdin if it's -
+    #[clap(long)]
+    from: Option<String>,
+    /// With --from, sources are separated by NULs, as from find -print0
+    #[clap(short = '0', long, requires = "from")]
+    null: bool,
+    /// Write what was done to this file, as JSON Lines, or CSV if its name ends
+    /// .csv. randos undo can use it to put everything back
+    #[clap(long)]
+    record: Option<String>,
+    /// With --record, carry on from a run which was interrupted: what the record
+    /// says was done counts towards the selection, its seed is used again, and
+    /// part-copied files are finished off
+    #[clap(long, requires = "record")]
+    resume: bool,
+    /// Copy or move this many files at once
+    #[clap(short, long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    jobs: u64,
+    /// Show a progress bar, with the throughput and time left
+    #[clap(long)]
+    progress: bool,
+    /// How mvx checks a copy before removing the source
+    #[clap(long, value_enum, default_value_t = Verify::Size)]
+    verify: Verify,
+    /// Have cp make copy-on-write clones where it can, like the reflink action
+    #[clap(long)]
+    auto_reflink: bool,
+    /// Pick files until they would fill this many bytes, like 4G or 700MB. Then
+    /// count is the most files to pick, and 0 means no limit
+    #[clap(long)]
+    budget: Option<String>,
+    /// With --budget, fill the space that's left with the biggest files that fit
+    #[clap(long, requires = "budget")]
+    best_fit: bool,
+    /// Never pick two files with the same contents
+    #[clap(long)]
+    unique_content: bool,
+    /// How to hash contents, for --unique-content, the content-hash scheme and
+    /// {hash} in a --template
+    #[clap(long, value_enum, default_value_t = Algorithm::Xxh3)]
+    hash: Algorithm,
+    /// With sync, how to put files in the destination
+    #[clap(long, value_enum, default_value_t = Action::Ln)]
+    via: Action,
+    /// With sync, the share of last time's files to replace, like 25%
+    #[clap(long, default_value = "100%")]
+    churn: String,
+    /// Action to perform
+    #[arg(required = true, value_enum)]
+    action: Mode,
     /// Operate on this many files
     #[arg(required = true)]
     count: usize,
-    /// Source files and/or directories
-    #[arg(required = true)]
-    sources: Vec<String>,
-    /// Destination directory
-    #[arg(required = true)]
-    dest_dir: String,
+    /// Source files and/or directories, then the destination directory. Give a
+    /// directory a weight with dir:weight. With --from, the sources can be left out
+    #[arg(required = true, value_name = "PATH")]
+    paths: Vec<String>,
 }
 
 fn parse_extensions(cli_ext_list: Option<String>) -> Option<Vec<String>> {
@@ -70,25 +221,129 @@ fn parse_regex(cli_regex: Option<String>) -> Option<Regex> {
     }
 }
 
-fn parse_age(cli_age: Option<u16>) -> Option<u64> {
+fn parse_age(cli_age: Option<String>) -> Option<u64> {
     match cli_age {
-        Some(days) => {
+        Some(age) => {
+            let Some(secs) = filter::parse_duration(&age) else {
+                die(format!("age '{}' is not a duration", age))
+            };
+
             let now = OffsetDateTime::now_utc();
-            let difference = Duration::days(days as i64);
+            let difference = Duration::seconds(secs.try_into().unwrap_or(i64::MAX));
             let cutoff = now.saturating_sub(difference).unix_timestamp();
-            Some(cutoff as u64)
+            Some(cutoff.max(0) as u64)
         }
         None => None,
     }
 }
 
+fn parse_file_size(cli_size: Option<String>) -> Option<u64> {
+    cli_size.map(|size| match budget::parse_size(&size) {
+        Some(bytes) => bytes,
+        None => die(format!("'{}' is not a size", size)),
+    })
+}
+
+fn parse_globs(cli_globs: &[String]) -> Vec<glob::Pattern> {
+    cli_globs
+        .iter()
+        .map(|g| match glob::Pattern::new(g) {
+            Ok(pattern) => pattern,
+            Err(_) => die(format!("glob '{}' cannot be compiled", g)),
+        })
+        .collect()
+}
+
+fn parse_churn(cli_churn: &str) -> f64 {
+    let percent = cli_churn.strip_suffix('%').unwrap_or(cli_churn);
+
+    match percent.parse::<f64>() {
+        Ok(percent) if (0.0..=100.0).contains(&percent) => percent / 100.0,
+        _ => die(format!(
+            "churn '{}' must be a percentage from 0 to 100",
+            cli_churn
+        )),
+    }
+}
+
+fn parse_budget(cli_budget: Option<String>) -> Option<u64> {
+    cli_budget.map(|size| match budget::parse_size(&size) {
+        Some(bytes) => bytes,
+        None => die(format!("budget '{}' is not a size", size)),
+    })
+}
+
+#[derive(Parser, Debug)]
+#[clap(name = "randos undo", version, about = "Puts back a run made with --record", long_about = None)]
+struct UndoCli {
+    /// Be verbose
+    #[clap(short, long)]
+    verbose: bool,
+    /// Say what would happen without doing it
+    #[clap(short, long)]
+    noop: bool,
+    /// The file written by --record
+    #[arg(required = true)]
+    record: String,
+}
+
+// Runs are undone last thing first, so a file moved twice ends up where it
+// started.
+fn undo(cli: UndoCli) -> ! {
+    let records = record::read(Utf8Path::new(&cli.record))
+        .unwrap_or_else(|e| die(format!("could not read {}: {}", cli.record, e)));
+
+    let mut exit_code = 0;
+
+    for entry in records.iter().rev() {
+        if let Err(e) = record::undo(entry, cli.verbose, cli.noop) {
+            eprintln!("ERROR: {}: {}", entry.dest, e);
+            exit_code = 1;
+        }
+    }
+
+    std::process::exit(exit_code);
+}
+
+// Whether an argument is one of undo's own flags, which may come before the
+// word undo, as in randos -v undo <record>.
+fn is_undo_flag(arg: &str) -> bool {
+    match arg.strip_prefix('-') {
+        Some("-verbose" | "-noop") => true,
+        Some(short) => !short.is_empty() && short.chars().all(|c| c == 'v' || c == 'n'),
+        None => false,
+    }
+}
+
 fn main() {
-    let cli = Cli::parse();
+    // undo takes different arguments, which clap can't tell apart from a
+    // selection's action.
+    let mut args: Vec<String> = std::env::args().collect();
+    let flags = args
+        .iter()
+        .skip(1)
+        .take_while(|arg| is_undo_flag(arg))
+        .count();
+
+    if args.get(flags + 1).map(String::as_str) == Some("undo") {
+        args[0] = args.remove(flags + 1);
+        undo(UndoCli::parse_from(args));
+    }
+
+    let mut cli = Cli::parse();
+
+    // clap can't have optional sources before the destination, so they come
+    // as one list.
+    let cli_dest_dir = cli.paths.pop().unwrap_or_default();
+
+    if cli.paths.is_empty() && cli.from.is_none() {
+        die("no sources given".to_string());
+    }
 
-    let dest_dir = match Utf8PathBuf::from(&cli.dest_dir).canonicalize_utf8() {
+    let dest_dir = match Utf8PathBuf::from(&cli_dest_dir).canonicalize_utf8() {
         Ok(dir) => dir,
         Err(_) => {
-            eprintln!("ERROR: {} does not exist", cli.dest_dir);
+            eprintln!("ERROR: {} does not exist", cli_dest_dir);
             std::process::exit(1);
         }
     };
@@ -98,64 +353,353 @@ fn main() {
         older: parse_age(cli.older),
         newer: parse_age(cli.newer),
         regex: parse_regex(cli.regex),
+        exclude: cli
+            .exclude
+            .iter()
+            .filter_map(|rx| parse_regex(Some(rx.clone())))
+            .collect(),
+        exclude_globs: parse_globs(&cli.exclude_glob),
+        full_path: cli.full_path,
+        min_size: parse_file_size(cli.min_size),
+        max_size: parse_file_size(cli.max_size),
+        types: (!cli.types.is_empty()).then_some(cli.types),
     };
 
-    let cli_dirs = dir::pathbuf_set(&cli.sources);
+    let template = cli
+        .template
+        .as_ref()
+        .map(|template| Template::parse(template, cli.hash).unwrap_or_else(|e| die(e.to_string())));
 
-    let candidate_pool = match dir::expand_file_list(&cli_dirs, cli.recurse) {
+    let namer: &dyn Namer = match &template {
+        Some(template) => template,
+        None => cli.scheme.namer(cli.hash),
+    };
+
+    let recurse = cli.recurse || cli.max_depth.is_some();
+    let max_depth = cli.max_depth.map(|depth| depth as usize);
+
+    let cli_sources = match dir::weighted_sources(&cli.paths) {
         Ok(list) => list,
-        Err(e) => die(format!("could not generate candidate list: {}", e)),
+        Err(e) => die(e.to_string()),
     };
 
-    let candidates = candidate_pool.len();
+    let weighted = cli_sources.iter().any(|(_path, weight)| *weight != 1.0);
 
-    let required_sources = if cli.count <= candidates {
-        cli.count
-    } else {
-        candidates
+    let balance = match cli.balance {
+        Some(balance) => balance,
+        None if weighted => Balance::Sources,
+        None => Balance::Files,
     };
 
-    let mut sources: Vec<(&Utf8PathBuf, Utf8PathBuf)> = Vec::new();
-    let mut index_list: Vec<usize> = (0..candidates).collect();
-    let mut rng = rng();
-    index_list.shuffle(&mut rng);
-    let mut seq_no = 0;
-
-    while let Some(index) = index_list.pop() {
-        let candidate = &candidate_pool[index];
-        if filter::is_candidate(candidate, &filter_opts)
-            && let Some(target_basename) = namer::name_from(candidate, seq_no, &cli.scheme)
-        {
-            sources.push((candidate, dest_dir.join(target_basename)));
-            seq_no += 1;
-            if sources.len() == required_sources {
-                break;
+    if weighted && balance != Balance::Sources {
+        eprintln!("WARNING: source weights are only used with --balance sources");
+    }
+
+    let mut cli_dirs: Vec<Utf8PathBuf> = cli_sources.iter().map(|(path, _)| path.clone()).collect();
+
+    if let Some(from) = &cli.from {
+        let listed = if from == "-" {
+            dir::listed_sources(io::stdin().lock(), cli.null)
+        } else {
+            File::open(from).and_then(|f| dir::listed_sources(BufReader::new(f), cli.null))
+        };
+
+        match listed {
+            Ok(listed) => cli_dirs.extend(listed),
+            Err(e) => die(format!("could not read sources from {}: {}", from, e)),
+        }
+    }
+
+    // With --resume, what the record says was done last time still counts, and
+    // the picks carry on from the same seed.
+    let recorded: Vec<Record> = match &cli.record {
+        Some(file) if cli.resume && Utf8Path::new(file).exists() => {
+            record::read(Utf8Path::new(file))
+                .unwrap_or_else(|e| die(format!("could not read {}: {}", file, e)))
+        }
+        _ => Vec::new(),
+    };
+
+    let seed = cli
+        .seed
+        .or(recorded.first().map(|entry| entry.seed))
+        .unwrap_or_else(rand::random);
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    if cli.verbose {
+        println!("seed: {}", seed);
+    }
+
+    let syncing = cli.action == Mode::Sync;
+    let budget = parse_budget(cli.budget);
+
+    let count = match budget {
+        Some(_) if cli.count == 0 => usize::MAX,
+        _ => cli.count,
+    };
+
+    if syncing && cli.via.handler().moves() {
+        die("sync can only link or copy files".to_string());
+    }
+
+    let mut manifest = syncing.then(|| {
+        Manifest::load(&dest_dir)
+            .unwrap_or_else(|e| die(format!("could not read sync manifest: {}", e)))
+    });
+
+    let mut exit_code = 0;
+    let mut in_use = InUse::default();
+    let mut picked: HashSet<Utf8PathBuf> = HashSet::new();
+    let mut unique = cli.unique_content.then(|| UniqueContent::new(cli.hash));
+
+    if let Some(manifest) = manifest.as_mut() {
+        // Last time's files which are being replaced. Their names are only
+        // free once they've gone, and any we can't remove stay in the
+        // manifest, so we can try again next time.
+        for (name, source) in manifest.churn(count, parse_churn(&cli.churn), &mut rng) {
+            let dest = dest_dir.join(&name);
+
+            if cli.verbose || cli.noop {
+                println!("rm: {}", dest);
+            }
+
+            if cli.noop {
+                in_use.freed.insert(dest);
+                continue;
+            }
+
+            match sync::remove(&dest) {
+                Ok(()) => {
+                    sync::remove_empty_dirs(&dest, &dest_dir);
+                    in_use.freed.insert(dest);
+                }
+                Err(e) => {
+                    eprintln!("ERROR: {}", e);
+                    exit_code = 1;
+                    manifest.entries.push((name, source));
+                }
             }
         }
+
+        manifest.entries.sort();
+
+        for (name, source) in manifest.entries.iter() {
+            in_use.taken.insert(dest_dir.join(name));
+            picked.insert(source.clone());
+
+            if let Some(unique) = unique.as_mut() {
+                unique.record(source);
+            }
+        }
+    }
+
+    let resumed: Vec<Record> = recorded
+        .into_iter()
+        .filter(|entry| entry.dest.symlink_metadata().is_ok())
+        .collect();
+
+    for entry in resumed.iter() {
+        in_use.taken.insert(entry.dest.clone());
+        picked.insert(entry.source.clone());
+
+        if let Some(unique) = unique.as_mut() {
+            unique.record(&entry.source);
+        }
+    }
+
+    if cli.verbose && !resumed.is_empty() {
+        println!("resuming: {} files already done", resumed.len());
+    }
+
+    let wanted = count.saturating_sub(picked.len());
+
+    let mut history = cli.history.as_ref().map(|file| {
+        History::load(Utf8Path::new(file))
+            .unwrap_or_else(|e| die(format!("could not read history {}: {}", file, e)))
+    });
+
+    if let Some(history) = history.as_mut() {
+        for entry in resumed.iter() {
+            history.record(&entry.source);
+        }
+    }
+
+    // Walks the sources, sorting the files worth picking into strata. A budget
+    // or --unique-content can turn down any number of picks, so then we keep
+    // everything. Otherwise we only need a sample the size of the selection.
+    let sample_size = (budget.is_none() && unique.is_none()).then_some(wanted);
+
+    let gather = |size: Option<usize>,
+                  picked: &HashSet<Utf8PathBuf>,
+                  history: &Option<History>,
+                  rng: &mut StdRng| {
+        let mut stratifier = Stratifier::new(balance, &cli_sources, cli.bucket_days, size);
+
+        dir::walk_files(&cli_dirs, recurse, max_depth, |file| {
+            if filter::is_candidate(&file, &filter_opts)
+                && !picked.contains(&file)
+                && !(cli.no_repeat && history.as_ref().is_some_and(|h| h.contains(&file)))
+            {
+                stratifier.offer(file, rng);
+            }
+        });
+
+        stratifier
+    };
+
+    let mut selection = Selection {
+        namer,
+        collisions: cli.collisions,
+        dest_dir: &dest_dir,
+        tree_roots: cli.preserve_tree.then_some(cli_dirs.as_slice()),
+        budget,
+        wanted,
+        verbose: cli.verbose,
+        used: picked.iter().map(|f| budget::file_size(f)).sum(),
+        in_use,
+        picked,
+        unique,
+        seq_no: 0,
+        sources: Vec::new(),
+        spare: Vec::new(),
+        ran_out: false,
+    };
+
+    let sampler = selection.pick_random(gather, sample_size, cli.no_repeat, &mut history, &mut rng);
+
+    if cli.best_fit {
+        selection.best_fit(sampler, &mut rng);
+    }
+
+    let sources = selection.sources;
+
+    if let Some(budget) = budget
+        && cli.verbose
+    {
+        println!("budget: {} of {} bytes used", selection.used, budget);
     }
 
-    if sources.len() < cli.count {
+    if sources.len() < wanted && count != usize::MAX && (budget.is_none() || selection.ran_out) {
         println!(
             "WARNING: requested {} files, but {} suitable candidates were found",
-            cli.count,
-            sources.len()
+            count,
+            sources.len() + count - wanted
         );
     }
 
     let action_opts = ActionOpts {
-        action: cli.action,
+        action: match cli.action {
+            Mode::Act(action) => action,
+            Mode::Sync => cli.via,
+        },
+        auto_reflink: cli.auto_reflink,
         noop: cli.noop,
         relative_links: cli.relative,
         verbose: cli.verbose,
+        verify: cli.verify,
     };
 
-    let mut exit_code = 0;
+    let mut record_writer = cli.record.as_ref().filter(|_| !cli.noop).map(|file| {
+        let mut writer = record::Writer::create(Utf8Path::new(file))
+            .unwrap_or_else(|e| die(format!("could not write {}: {}", file, e)));
 
-    for (source, dest) in sources {
-        if let Err(e) = actions::run(source, &dest, &action_opts) {
-            eprintln!("ERROR: {}", e);
-            exit_code = 1;
+        for entry in resumed.iter() {
+            writer
+                .write(entry)
+                .unwrap_or_else(|e| die(format!("could not write {}: {}", file, e)));
         }
+
+        writer
+    });
+
+    let copying = action_opts.action.handler().copies();
+    let showing_progress = cli.progress && !cli.noop;
+    let recording = record_writer.is_some();
+
+    // A move takes the source away, so look at it first. Only the record wants
+    // mtimes, and only it and the progress bar want sizes.
+    let jobs: Vec<Job> = sources
+        .into_iter()
+        .map(|(source, dest)| {
+            let size = if recording || (showing_progress && copying) {
+                budget::file_size(&source)
+            } else {
+                0
+            };
+
+            let mtime = recording.then(|| filter::file_mtime(&source)).flatten();
+            (source, dest, size, mtime)
+        })
+        .collect();
+
+    // Half-made copies which this run won't carry on with are no use now.
+    if !cli.noop {
+        let jobs = jobs.iter().map(|(source, dest, _, _)| (source, dest));
+
+        for part in actions::unclaimed_parts(&dest_dir, jobs) {
+            if cli.verbose {
+                println!("rm: {}", part);
+            }
+
+            if let Err(e) = fs::remove_file(&part) {
+                eprintln!("ERROR: could not remove {}: {}", part, e);
+                exit_code = 1;
+            }
+        }
+    }
+
+    jobs::run(
+        &jobs,
+        &action_opts,
+        cli.jobs,
+        showing_progress,
+        |(source, dest, size, mtime), result| {
+            if let Err(e) = result {
+                eprintln!("ERROR: {}", e);
+                exit_code = 1;
+                return;
+            }
+
+            if let Some(writer) = record_writer.as_mut()
+                && let Err(e) = writer.write(&Record {
+                    source: source.clone(),
+                    dest: dest.clone(),
+                    action: action_opts.action,
+                    size: *size,
+                    mtime: mtime.unwrap_or_default(),
+                    seed,
+                })
+            {
+                eprintln!("ERROR: could not record {}: {}", dest, e);
+                exit_code = 1;
+            }
+
+            if let Some(history) = history.as_mut() {
+                history.record(source);
+            }
+
+            if let Some(manifest) = manifest.as_mut()
+                && let Ok(name) = dest.strip_prefix(&dest_dir)
+            {
+                manifest.entries.push((name.to_string(), source.clone()));
+            }
+        },
+    );
+
+    if let Some(history) = history
+        && !cli.noop
+        && let Err(e) = history.save()
+    {
+        eprintln!("ERROR: could not write history: {}", e);
+        exit_code = 1;
+    }
+
+    if let Some(manifest) = manifest
+        && !cli.noop
+        && let Err(e) = manifest.save()
+    {
+        eprintln!("ERROR: could not write sync manifest: {}", e);
+        exit_code = 1;
     }
 
     std::process::exit(exit_code);
@@ -0,0 +1,227 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use rand::rngs::StdRng;
+use randos::utils::content::UniqueContent;
+use randos::utils::history::History;
+use randos::utils::namer::{self, Collision, InUse, Namer};
+use randos::utils::sample::{Sampler, Stratifier};
+use randos::utils::{budget, dir};
+use std::collections::HashSet;
+
+// Making a run's picks from the sampled candidates: naming each one in the
+// destination, and keeping to --budget and --unique-content.
+
+pub struct Selection<'a> {
+    pub namer: &'a dyn Namer,
+    pub collisions: Collision,
+    pub dest_dir: &'a Utf8Path,
+    // With --preserve-tree, the source directories, so each pick goes in the
+    // same place under dest_dir as it is under its source.
+    pub tree_roots: Option<&'a [Utf8PathBuf]>,
+    pub budget: Option<u64>,
+    pub wanted: usize,
+    pub verbose: bool,
+    pub in_use: InUse,
+    pub picked: HashSet<Utf8PathBuf>,
+    pub unique: Option<UniqueContent>,
+    pub seq_no: usize,
+    // Bytes taken by everything picked, including last time's files.
+    pub used: u64,
+    // This run's picks, and where they go.
+    pub sources: Vec<(Utf8PathBuf, Utf8PathBuf)>,
+    // Picks which didn't fit the budget, for best_fit().
+    pub spare: Vec<Utf8PathBuf>,
+    // Whether there weren't enough candidates.
+    pub ran_out: bool,
+}
+
+impl Selection<'_> {
+    fn is_full(&self) -> bool {
+        self.sources.len() >= self.wanted
+    }
+
+    // Hashing is the slowest check, so it comes last.
+    fn wants(&mut self, candidate: &Utf8PathBuf) -> bool {
+        !self.picked.contains(candidate)
+            && self.unique.as_mut().is_none_or(|u| u.is_unique(candidate))
+    }
+
+    // Picks at random until there are enough, or the next pick would go over
+    // the budget. gather walks the sources for a sample of the given size, or
+    // everything, leaving out what's been picked. When the sample runs out we
+    // walk them again, for everything if the sample was cut short, and with
+    // --no-repeat, for a new cycle of the history. Returns what's left of the
+    // sample, for best_fit().
+    pub fn pick_random<'s>(
+        &mut self,
+        mut gather: impl FnMut(
+            Option<usize>,
+            &HashSet<Utf8PathBuf>,
+            &Option<History>,
+            &mut StdRng,
+        ) -> Stratifier<'s>,
+        sample_size: Option<usize>,
+        no_repeat: bool,
+        history: &mut Option<History>,
+        rng: &mut StdRng,
+    ) -> Sampler {
+        let stratifier = gather(sample_size, &self.picked, history, rng);
+        let mut truncated = stratifier.is_truncated();
+        let mut sampler = Sampler::new(stratifier.into_strata(), rng);
+        let mut new_cycle = no_repeat;
+
+        while !self.is_full() {
+            let Some(candidate) = sampler.next_accepted(|c| self.wants(c)) else {
+                if truncated {
+                    // Some picks were turned down, and the sample has run out.
+                    // Look at everything this time.
+                    truncated = false;
+                    let stratifier = gather(None, &self.picked, history, rng);
+                    sampler = Sampler::new(stratifier.into_strata(), rng);
+                    continue;
+                }
+
+                if new_cycle {
+                    // Everything has been picked, so forget the history and go
+                    // round again, without repeating anything from this run.
+                    if self.verbose {
+                        println!("all candidates have been picked: starting a new cycle");
+                    }
+
+                    if let Some(history) = history.as_mut() {
+                        history.new_cycle();
+                    }
+
+                    new_cycle = false;
+                    let stratifier = gather(sample_size, &self.picked, history, rng);
+                    truncated = stratifier.is_truncated();
+                    sampler = Sampler::new(stratifier.into_strata(), rng);
+                    continue;
+                }
+
+                self.ran_out = true;
+                break;
+            };
+
+            let size = match self.budget {
+                Some(_) => budget::file_size(&candidate),
+                None => 0,
+            };
+
+            if self.budget.is_some_and(|budget| self.used + size > budget) {
+                self.spare.push(candidate);
+                break;
+            }
+
+            self.take(candidate, size, rng);
+        }
+
+        sampler
+    }
+
+    // The random picks have stopped because the next one didn't fit. Now see
+    // what else does.
+    pub fn best_fit(&mut self, mut sampler: Sampler, rng: &mut StdRng) {
+        let Some(budget) = self.budget else {
+            return;
+        };
+
+        if self.is_full() {
+            return;
+        }
+
+        while let Some(candidate) = sampler.next_accepted(|c| self.wants(c)) {
+            self.spare.push(candidate);
+        }
+
+        let spare = std::mem::take(&mut self.spare);
+
+        for candidate in budget::best_fit(spare, budget.saturating_sub(self.used)) {
+            if self.is_full() {
+                break;
+            }
+
+            // The spare files were only checked against the random picks.
+            if self
+                .unique
+                .as_mut()
+                .is_some_and(|u| !u.is_unique(&candidate))
+            {
+                continue;
+            }
+
+            let size = budget::file_size(&candidate);
+            self.take(candidate, size, rng);
+        }
+    }
+
+    // Adds a pick, if it can be named in the destination.
+    fn take(&mut self, candidate: Utf8PathBuf, size: u64, rng: &mut StdRng) {
+        let dir = match self.tree_roots {
+            Some(roots) => self.dest_dir.join(dir::dir_under_root(&candidate, roots)),
+            None => self.dest_dir.to_path_buf(),
+        };
+
+        let Some(dest) = dest_for(
+            &candidate,
+            &mut self.seq_no,
+            self.namer,
+            self.collisions,
+            rng,
+            &dir,
+            &self.in_use,
+        ) else {
+            if self.verbose {
+                println!(
+                    "skipping {}: no name for it in {}",
+                    candidate, self.dest_dir
+                );
+            }
+
+            return;
+        };
+
+        if let Some(unique) = self.unique.as_mut() {
+            unique.record(&candidate);
+        }
+
+        self.used += size;
+        self.picked.insert(candidate.clone());
+        self.in_use.taken.insert(dest.clone());
+        self.sources.push((candidate, dest));
+    }
+}
+
+// Names a pick in the destination, without clobbering anything. If the name
+// is taken, we try the scheme's next name, if it has one, and then fall back
+// on the collision policy.
+fn dest_for(
+    candidate: &Utf8PathBuf,
+    seq_no: &mut usize,
+    namer: &dyn Namer,
+    collision: Collision,
+    rng: &mut StdRng,
+    dest_dir: &Utf8Path,
+    in_use: &InUse,
+) -> Option<Utf8PathBuf> {
+    let taken = |name: &str| in_use.contains(&dest_dir.join(name));
+    let first = namer::name_with(candidate, *seq_no, namer, rng);
+    let mut target = first.clone();
+
+    while let Some(target_basename) = &target
+        && taken(target_basename)
+    {
+        *seq_no += 1;
+        let next = namer::name_with(candidate, *seq_no, namer, rng);
+        target = next.filter(|next| next != target_basename);
+    }
+
+    *seq_no += 1;
+
+    if target.is_none()
+        && let Some(first) = first
+    {
+        target = namer::avoid_collision(&first, candidate, collision, taken);
+    }
+
+    target.map(|target_basename| dest_dir.join(target_basename))
+}
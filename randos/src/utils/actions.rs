@@ -1,9 +1,223 @@
+use crate::utils::content::{self, Algorithm};
 use crate::utils::types::ActionOpts;
-use camino::Utf8PathBuf;
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::ValueEnum;
+use filetime::FileTime;
 use pathdiff::diff_utf8_paths;
-use std::{fs, io};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// What can be done with a pick. Each action has a FileAction, which does it,
+// and says how it behaves, so adding an action means adding a variant here
+// and an arm in handler().
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    /// Symlink
+    #[value(alias = "symlink")]
+    #[serde(alias = "symlink")]
+    Ln,
+    /// Hard link
+    #[value(alias = "hardlink")]
+    #[serde(alias = "hardlink")]
+    Lnh,
+    /// Copy
+    #[value(alias = "copy")]
+    #[serde(alias = "copy")]
+    Cp,
+    /// Copy-on-write clone, or a copy where that can't be done
+    Reflink,
+    /// Move
+    #[value(alias = "move")]
+    #[serde(alias = "move")]
+    Mv,
+    /// Move across filesystems, by copying then removing the source
+    #[value(alias = "movexfs")]
+    #[serde(alias = "movexfs")]
+    Mvx,
+}
+
+impl Action {
+    pub fn handler(self) -> &'static dyn FileAction {
+        match self {
+            Action::Ln => &SymlinkAction,
+            Action::Lnh => &HardLinkAction,
+            Action::Cp => &CopyAction,
+            Action::Reflink => &ReflinkAction,
+            Action::Mv => &MoveAction,
+            Action::Mvx => &MoveXfsAction,
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => write!(f, "{}", value.get_name()),
+            None => write!(f, "{:?}", self),
+        }
+    }
+}
+
+pub trait FileAction: Sync {
+    // Adds the bytes copied to copied as it goes, for progress.
+    fn run(
+        &self,
+        source: &Utf8PathBuf,
+        dest: &Utf8PathBuf,
+        opts: &ActionOpts,
+        copied: &AtomicU64,
+    ) -> io::Result<()>;
+
+    // True if the source is gone afterwards.
+    fn moves(&self) -> bool {
+        false
+    }
+
+    // True if the file's contents are written out, so progress is in bytes.
+    fn copies(&self) -> bool {
+        false
+    }
+
+    // True if dest, which is size bytes, is still what we made, so undo can
+    // remove it.
+    fn made(&self, dest: &fs::Metadata, size: u64) -> bool {
+        dest.is_file() && dest.len() == size
+    }
+}
+
+struct SymlinkAction;
+struct HardLinkAction;
+struct CopyAction;
+struct ReflinkAction;
+struct MoveAction;
+struct MoveXfsAction;
+
+impl FileAction for SymlinkAction {
+    fn run(
+        &self,
+        source: &Utf8PathBuf,
+        dest: &Utf8PathBuf,
+        opts: &ActionOpts,
+        _: &AtomicU64,
+    ) -> io::Result<()> {
+        symlink(source, dest, opts.relative_links)
+    }
+
+    fn made(&self, dest: &fs::Metadata, _size: u64) -> bool {
+        dest.is_symlink()
+    }
+}
+
+impl FileAction for HardLinkAction {
+    fn run(
+        &self,
+        source: &Utf8PathBuf,
+        dest: &Utf8PathBuf,
+        _: &ActionOpts,
+        _: &AtomicU64,
+    ) -> io::Result<()> {
+        fs::hard_link(source, dest)
+    }
+}
+
+impl FileAction for CopyAction {
+    fn run(
+        &self,
+        source: &Utf8PathBuf,
+        dest: &Utf8PathBuf,
+        opts: &ActionOpts,
+        copied: &AtomicU64,
+    ) -> io::Result<()> {
+        if opts.auto_reflink {
+            reflink(source, dest, copied)
+        } else {
+            copy(source, dest, copied)
+        }
+    }
+
+    fn copies(&self) -> bool {
+        true
+    }
+}
+
+impl FileAction for ReflinkAction {
+    fn run(
+        &self,
+        source: &Utf8PathBuf,
+        dest: &Utf8PathBuf,
+        _: &ActionOpts,
+        copied: &AtomicU64,
+    ) -> io::Result<()> {
+        reflink(source, dest, copied)
+    }
+
+    fn copies(&self) -> bool {
+        true
+    }
+}
+
+impl FileAction for MoveAction {
+    fn run(
+        &self,
+        source: &Utf8PathBuf,
+        dest: &Utf8PathBuf,
+        _: &ActionOpts,
+        _: &AtomicU64,
+    ) -> io::Result<()> {
+        rename(source, dest)
+    }
+
+    fn moves(&self) -> bool {
+        true
+    }
+}
+
+impl FileAction for MoveXfsAction {
+    fn run(
+        &self,
+        source: &Utf8PathBuf,
+        dest: &Utf8PathBuf,
+        opts: &ActionOpts,
+        copied: &AtomicU64,
+    ) -> io::Result<()> {
+        movexfs(source, dest, opts.verify, copied)
+    }
+
+    fn moves(&self) -> bool {
+        true
+    }
+
+    fn copies(&self) -> bool {
+        true
+    }
+}
+
+// How mvx checks a copy before it removes the source.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Verify {
+    /// The copy is the same size
+    #[default]
+    Size,
+    /// The copy has the same SHA1
+    Hash,
+}
 
 pub fn run(source: &Utf8PathBuf, dest: &Utf8PathBuf, opts: &ActionOpts) -> io::Result<()> {
+    run_counted(source, dest, opts, &AtomicU64::new(0))
+}
+
+// Like run(), adding the bytes copied to copied as they go, for progress.
+pub fn run_counted(
+    source: &Utf8PathBuf,
+    dest: &Utf8PathBuf,
+    opts: &ActionOpts,
+    copied: &AtomicU64,
+) -> io::Result<()> {
     if opts.verbose || opts.noop {
         println!("{}: {} -> {}", opts.action, source, dest);
     }
@@ -12,13 +226,11 @@ pub fn run(source: &Utf8PathBuf, dest: &Utf8PathBuf, opts: &ActionOpts) -> io::R
         return Ok(());
     }
 
-    match opts.action.as_str() {
-        "cp" | "copy" => fs::copy(source, dest).map(|_| ()),
-        "mv" | "move" => fs::rename(source, dest),
-        "mvx" | "movexfs" => movexfs(source, dest),
-        "lnh" | "hardlink" => fs::hard_link(source, dest),
-        _ => symlink(source, dest, opts.relative_links),
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
     }
+
+    opts.action.handler().run(source, dest, opts, copied)
 }
 
 fn symlink(source: &Utf8PathBuf, target: &Utf8PathBuf, relative: bool) -> io::Result<()> {
@@ -40,7 +252,379 @@ fn symlink(source: &Utf8PathBuf, target: &Utf8PathBuf, relative: bool) -> io::Re
     std::os::unix::fs::symlink(source, target)
 }
 
-fn movexfs(source: &Utf8PathBuf, dest: &Utf8PathBuf) -> io::Result<()> {
-    fs::copy(source, dest).map(|_| ())?;
+fn already_exists(dest: &Utf8Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("{} already exists", dest),
+    )
+}
+
+const PART_SUFFIX: &str = ".randos-part";
+
+// Where a copy is made before it's put in place. It's named for the source, its
+// size and its mtime as well as the destination, so an interrupted copy is only
+// carried on with if the next run picks the same, unchanged, file for the same
+// place.
+fn partial_name(source: &Utf8Path, metadata: &fs::Metadata, dest: &Utf8Path) -> Utf8PathBuf {
+    let mtime = FileTime::from_last_modification_time(metadata);
+    let mut hasher = sha1_smol::Sha1::new();
+    hasher.update(
+        format!(
+            "{}\0{}\0{}.{}",
+            source,
+            metadata.len(),
+            mtime.unix_seconds(),
+            mtime.nanoseconds()
+        )
+        .as_bytes(),
+    );
+    let digest = hasher.digest().to_string();
+
+    dest.with_file_name(format!(
+        ".{}.{}{}",
+        dest.file_name().unwrap_or_default(),
+        &digest[..8],
+        PART_SUFFIX
+    ))
+}
+
+// Part files which none of the jobs would carry on with, in dest_dir and the
+// jobs' destination directories. They're left by interrupted copies of files
+// which weren't picked again, or which have changed since.
+pub fn unclaimed_parts<'a, I>(dest_dir: &Utf8Path, jobs: I) -> Vec<Utf8PathBuf>
+where
+    I: IntoIterator<Item = (&'a Utf8PathBuf, &'a Utf8PathBuf)>,
+{
+    let mut dirs: HashSet<Utf8PathBuf> = HashSet::from([dest_dir.to_path_buf()]);
+    let mut claimed: HashSet<Utf8PathBuf> = HashSet::new();
+
+    for (source, dest) in jobs {
+        if let Some(dir) = dest.parent() {
+            dirs.insert(dir.to_path_buf());
+        }
+
+        if let Ok(metadata) = fs::metadata(source) {
+            claimed.insert(partial_name(source, &metadata, dest));
+        }
+    }
+
+    let mut ret: Vec<Utf8PathBuf> = dirs
+        .iter()
+        .filter_map(|dir| dir.read_dir_utf8().ok())
+        .flatten()
+        .flatten()
+        .map(|entry| entry.into_path())
+        .filter(|file| {
+            file.file_name()
+                .is_some_and(|name| name.starts_with('.') && name.ends_with(PART_SUFFIX))
+        })
+        .filter(|file| !claimed.contains(file))
+        .collect();
+
+    ret.sort();
+    ret
+}
+
+// Names are checked when files are picked, but something else might get there
+// first, and fs::copy() and fs::rename() would quietly replace it. The copy
+// keeps the source's permissions and times.
+fn copy(source: &Utf8PathBuf, dest: &Utf8PathBuf, copied: &AtomicU64) -> io::Result<()> {
+    if dest.symlink_metadata().is_ok() {
+        return Err(already_exists(dest));
+    }
+
+    let mut reader = File::open(source)?;
+    let metadata = reader.metadata()?;
+    let part = partial_name(source, &metadata, dest);
+
+    let mut writer = OpenOptions::new().create(true).append(true).open(&part)?;
+
+    let mut done = writer.metadata()?.len();
+
+    if done > metadata.len() {
+        writer.set_len(0)?;
+        done = 0;
+    }
+
+    reader.seek(SeekFrom::Start(done))?;
+    copied.fetch_add(done, Ordering::Relaxed);
+
+    let mut buf = vec![0; 1024 * 1024];
+
+    loop {
+        match reader.read(&mut buf)? {
+            0 => break,
+            n => {
+                writer.write_all(&buf[..n])?;
+                copied.fetch_add(n as u64, Ordering::Relaxed);
+            }
+        }
+    }
+
+    keep_metadata(&writer, &metadata)?;
+    drop(writer);
+
+    put_in_place(&part, dest)
+}
+
+fn keep_metadata(file: &File, metadata: &fs::Metadata) -> io::Result<()> {
+    file.set_permissions(metadata.permissions())?;
+    filetime::set_file_handle_times(
+        file,
+        Some(FileTime::from_last_access_time(metadata)),
+        Some(FileTime::from_last_modification_time(metadata)),
+    )
+}
+
+// A copy-on-write clone shares the source's blocks until either is changed,
+// so it takes no time or space. Where the filesystem can't do that, or the
+// source is on another one, it's a normal copy, like cp --reflink=auto.
+fn reflink(source: &Utf8PathBuf, dest: &Utf8PathBuf, copied: &AtomicU64) -> io::Result<()> {
+    let reader = File::open(source)?;
+    let metadata = reader.metadata()?;
+    let part = partial_name(source, &metadata, dest);
+
+    // An earlier run has already fallen back to copying.
+    if dest.symlink_metadata().is_ok() || part.exists() {
+        return copy(source, dest, copied);
+    }
+
+    let writer = File::create_new(&part)?;
+
+    if clone_file(&reader, &writer).is_err() {
+        drop(writer);
+        fs::remove_file(&part)?;
+        return copy(source, dest, copied);
+    }
+
+    copied.fetch_add(metadata.len(), Ordering::Relaxed);
+    keep_metadata(&writer, &metadata)?;
+    drop(writer);
+
+    put_in_place(&part, dest)
+}
+
+#[cfg(target_os = "linux")]
+fn clone_file(source: &File, dest: &File) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: FICLONE only reads the two descriptors, which are open for as
+    // long as we hold the Files.
+    match unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn clone_file(_source: &File, _dest: &File) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+// A hard link can't replace anything, so it's the safe way to move the copy
+// into place. Not every filesystem has them, though.
+fn put_in_place(part: &Utf8Path, dest: &Utf8Path) -> io::Result<()> {
+    match fs::hard_link(part, dest) {
+        Ok(()) => fs::remove_file(part),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(already_exists(dest)),
+        Err(_) if dest.symlink_metadata().is_ok() => Err(already_exists(dest)),
+        Err(_) => fs::rename(part, dest),
+    }
+}
+
+fn rename(source: &Utf8PathBuf, dest: &Utf8PathBuf) -> io::Result<()> {
+    if dest.symlink_metadata().is_ok() {
+        return Err(already_exists(dest));
+    }
+
+    fs::rename(source, dest)
+}
+
+// The source only goes once we're sure the copy is good.
+fn movexfs(
+    source: &Utf8PathBuf,
+    dest: &Utf8PathBuf,
+    verify: Verify,
+    copied: &AtomicU64,
+) -> io::Result<()> {
+    copy(source, dest, copied)?;
+
+    let good = match verify {
+        Verify::Size => fs::metadata(source)?.len() == fs::metadata(dest)?.len(),
+        Verify::Hash => {
+            content::digest(source, Algorithm::Sha1)? == content::digest(dest, Algorithm::Sha1)?
+        }
+    };
+
+    if !good {
+        fs::remove_file(dest)?;
+        return Err(io::Error::other(format!(
+            "the copy of {} didn't match: leaving it where it is",
+            source
+        )));
+    }
+
     fs::remove_file(source)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use camino_tempfile_ext::prelude::*;
+
+    fn opts(action: Action) -> ActionOpts {
+        ActionOpts {
+            action,
+            auto_reflink: false,
+            noop: false,
+            relative_links: false,
+            verbose: false,
+            verify: Verify::Hash,
+        }
+    }
+
+    #[test]
+    fn test_run_never_overwrites() {
+        let dir = Utf8TempDir::new().unwrap();
+        dir.child("src").write_str("new").unwrap();
+        dir.child("dest").write_str("old").unwrap();
+
+        let source = dir.path().join("src");
+        let dest = dir.path().join("dest");
+
+        for action in [
+            Action::Cp,
+            Action::Reflink,
+            Action::Mv,
+            Action::Mvx,
+            Action::Lnh,
+            Action::Ln,
+        ] {
+            assert!(run(&source, &dest, &opts(action)).is_err());
+            assert_eq!("old", fs::read_to_string(&dest).unwrap());
+            assert!(source.exists());
+        }
+
+        let fresh = dir.path().join("fresh");
+        assert!(run(&source, &fresh, &opts(Action::Cp)).is_ok());
+        assert_eq!("new", fs::read_to_string(&fresh).unwrap());
+    }
+
+    #[test]
+    fn test_copy_keeps_times_and_permissions() {
+        let dir = Utf8TempDir::new().unwrap();
+        dir.child("src").write_str("content").unwrap();
+
+        let source = dir.path().join("src");
+        let mtime = FileTime::from_unix_time(1_600_000_000, 0);
+        filetime::set_file_mtime(&source, mtime).unwrap();
+
+        let mut permissions = fs::metadata(&source).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&source, permissions.clone()).unwrap();
+
+        for action in [Action::Cp, Action::Reflink, Action::Mvx] {
+            let dest = dir.path().join(format!("dest/{}", action));
+            assert!(run(&source, &dest, &opts(action)).is_ok());
+
+            let metadata = fs::metadata(&dest).unwrap();
+            assert_eq!(mtime, FileTime::from_last_modification_time(&metadata));
+            assert_eq!(permissions, metadata.permissions());
+            assert_eq!("content", fs::read_to_string(&dest).unwrap());
+        }
+
+        assert!(!source.exists());
+        assert_eq!(3, dir.path().join("dest").read_dir_utf8().unwrap().count());
+    }
+
+    #[test]
+    fn test_cp_auto_reflink() {
+        let dir = Utf8TempDir::new().unwrap();
+        dir.child("src").write_str("content").unwrap();
+
+        let source = dir.path().join("src");
+        let dest = dir.path().join("dest");
+        let opts = ActionOpts {
+            auto_reflink: true,
+            ..opts(Action::Cp)
+        };
+
+        // Cloned or copied, depending on the filesystem, but independent
+        // either way.
+        assert!(run(&source, &dest, &opts).is_ok());
+        assert_eq!("content", fs::read_to_string(&dest).unwrap());
+
+        fs::write(&dest, "changed").unwrap();
+        assert_eq!("content", fs::read_to_string(&source).unwrap());
+
+        assert!(run(&source, &dest, &opts).is_err());
+        assert_eq!("changed", fs::read_to_string(&dest).unwrap());
+    }
+
+    #[test]
+    fn test_copy_resumes() {
+        let dir = Utf8TempDir::new().unwrap();
+        dir.child("src").write_str("0123456789").unwrap();
+
+        let source = dir.path().join("src");
+        let dest = dir.path().join("dest");
+        let part = partial_name(&source, &fs::metadata(&source).unwrap(), &dest);
+        fs::write(&part, "01234").unwrap();
+
+        let copied = AtomicU64::new(0);
+        assert!(run_counted(&source, &dest, &opts(Action::Cp), &copied).is_ok());
+
+        assert_eq!("0123456789", fs::read_to_string(&dest).unwrap());
+        assert_eq!(10, copied.load(Ordering::Relaxed));
+        assert!(!part.exists());
+
+        // A partial copy bigger than its source is no use.
+        let dest = dir.path().join("dest2");
+        let part = partial_name(&source, &fs::metadata(&source).unwrap(), &dest);
+        fs::write(&part, "0123456789abc").unwrap();
+
+        assert!(run(&source, &dest, &opts(Action::Cp)).is_ok());
+        assert_eq!("0123456789", fs::read_to_string(&dest).unwrap());
+
+        // Nor is one of a source which has changed since.
+        let dest = dir.path().join("dest3");
+        let part = partial_name(&source, &fs::metadata(&source).unwrap(), &dest);
+        fs::write(&part, "01234").unwrap();
+        fs::write(&source, "abcdefghij").unwrap();
+        filetime::set_file_mtime(&source, FileTime::from_unix_time(1_600_000_000, 0)).unwrap();
+
+        assert!(run(&source, &dest, &opts(Action::Cp)).is_ok());
+        assert_eq!("abcdefghij", fs::read_to_string(&dest).unwrap());
+    }
+
+    #[test]
+    fn test_unclaimed_parts() {
+        let dir = Utf8TempDir::new().unwrap();
+        dir.child("src").write_str("0123456789").unwrap();
+        dir.child("dest/sub/keep").write_str("keep").unwrap();
+
+        let source = dir.path().join("src");
+        let dest_dir = dir.path().join("dest");
+        let dest = dest_dir.join("sub/file");
+        let claimed = partial_name(&source, &fs::metadata(&source).unwrap(), &dest);
+        let stale = dest_dir.join(".old.12345678.randos-part");
+        fs::write(&claimed, "01234").unwrap();
+        fs::write(&stale, "01234").unwrap();
+
+        assert_eq!(
+            vec![stale.clone()],
+            unclaimed_parts(&dest_dir, [(&source, &dest)])
+        );
+        assert_eq!(
+            vec![stale.clone()],
+            unclaimed_parts(&dest_dir, Vec::<(&Utf8PathBuf, &Utf8PathBuf)>::new())
+        );
+        assert_eq!(
+            vec![claimed],
+            unclaimed_parts(
+                &dest_dir.join("sub"),
+                Vec::<(&Utf8PathBuf, &Utf8PathBuf)>::new()
+            )
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use std::fs;
+
+// Helpers for --budget, which picks files until they would fill a given number
+// of bytes, rather than picking a given number of files.
+
+// Sizes are bytes, or have a suffix like sort -h and friends: K, M, G and T
+// are powers of 1024, and KB, MB, GB and TB powers of 1000.
+pub fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+
+    let (number, unit) = size.split_at(split);
+    let number: f64 = number.parse().ok()?;
+
+    let multiplier: u64 = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KIB" => 1 << 10,
+        "M" | "MIB" => 1 << 20,
+        "G" | "GIB" => 1 << 30,
+        "T" | "TIB" => 1 << 40,
+        "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        "TB" => 1_000_000_000_000,
+        _ => return None,
+    };
+
+    Some((number * multiplier as f64) as u64)
+}
+
+pub fn file_size(file: &Utf8Path) -> u64 {
+    fs::metadata(file).map(|m| m.len()).unwrap_or(0)
+}
+
+// Fills as much of the room as it can, taking the biggest files first.
+pub fn best_fit(candidates: Vec<Utf8PathBuf>, mut room: u64) -> Vec<Utf8PathBuf> {
+    let mut sized: Vec<(u64, Utf8PathBuf)> = candidates
+        .into_iter()
+        .map(|file| (file_size(&file), file))
+        .collect();
+
+    sized.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    let mut ret = Vec::new();
+
+    for (size, file) in sized {
+        if size <= room {
+            room -= size;
+            ret.push(file);
+        }
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use camino_tempfile_ext::prelude::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(Some(100), parse_size("100"));
+        assert_eq!(Some(100), parse_size("100B"));
+        assert_eq!(Some(4 * 1024 * 1024 * 1024), parse_size("4G"));
+        assert_eq!(Some(4_000_000_000), parse_size("4GB"));
+        assert_eq!(Some(1536), parse_size("1.5k"));
+        assert_eq!(Some(2 * 1024 * 1024), parse_size("2MiB"));
+        assert_eq!(None, parse_size("4X"));
+        assert_eq!(None, parse_size("G"));
+        assert_eq!(None, parse_size(""));
+    }
+
+    #[test]
+    fn test_best_fit() {
+        let dir = Utf8TempDir::new().unwrap();
+
+        for (name, size) in [("a", 50), ("b", 30), ("c", 30), ("d", 15)] {
+            dir.child(name).write_str(&"x".repeat(size)).unwrap();
+        }
+
+        let files = |names: &[&str]| -> Vec<Utf8PathBuf> {
+            names.iter().map(|n| dir.path().join(n)).collect()
+        };
+
+        assert_eq!(
+            files(&["a", "b", "d"]),
+            best_fit(files(&["a", "b", "c", "d"]), 100)
+        );
+
+        assert_eq!(files(&["b", "d"]), best_fit(files(&["b", "c", "d"]), 45));
+        assert!(best_fit(files(&["a", "b"]), 10).is_empty());
+    }
+}
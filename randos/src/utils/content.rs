@@ -0,0 +1,145 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use xxhash_rust::xxh3::Xxh3;
+
+// Knowing files by what's in them, for --unique-content and the content-hash
+// naming scheme. Hashing is slow, so files are compared by size first, and
+// only hashed if another file the same size has been picked.
+
+// How --hash digests file contents.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Algorithm {
+    /// 128-bit XXH3: fast, but not for telling apart files made to clash
+    #[default]
+    Xxh3,
+    /// SHA1: slower, and what randos used before
+    Sha1,
+}
+
+// A file's digest, in hex: 32 digits with XXH3, or 40 with SHA1.
+pub fn digest(file: &Utf8Path, algorithm: Algorithm) -> io::Result<String> {
+    let mut reader = File::open(file)?;
+    let mut buf = vec![0; 64 * 1024];
+
+    match algorithm {
+        Algorithm::Xxh3 => {
+            let mut hasher = Xxh3::new();
+            read_all(&mut reader, &mut buf, |chunk| hasher.update(chunk))?;
+            Ok(format!("{:032x}", hasher.digest128()))
+        }
+        Algorithm::Sha1 => {
+            let mut hasher = sha1_smol::Sha1::new();
+            read_all(&mut reader, &mut buf, |chunk| hasher.update(chunk))?;
+            Ok(hasher.digest().to_string())
+        }
+    }
+}
+
+fn read_all(reader: &mut File, buf: &mut [u8], mut f: impl FnMut(&[u8])) -> io::Result<()> {
+    loop {
+        match reader.read(buf)? {
+            0 => return Ok(()),
+            n => f(&buf[..n]),
+        }
+    }
+}
+
+pub struct UniqueContent {
+    algorithm: Algorithm,
+    // Picked files, by size, with their digests once we've needed them.
+    picked: HashMap<u64, Vec<(Utf8PathBuf, Option<String>)>>,
+}
+
+impl UniqueContent {
+    pub fn new(algorithm: Algorithm) -> Self {
+        UniqueContent {
+            algorithm,
+            picked: HashMap::new(),
+        }
+    }
+
+    // True if nothing with the same content as file has been picked. Files
+    // which can't be read are taken to be unique.
+    pub fn is_unique(&mut self, file: &Utf8Path) -> bool {
+        let Ok(size) = fs::metadata(file).map(|m| m.len()) else {
+            return true;
+        };
+
+        let Some(same_size) = self.picked.get_mut(&size) else {
+            return true;
+        };
+
+        let Ok(file_digest) = digest(file, self.algorithm) else {
+            return true;
+        };
+
+        for (other, other_digest) in same_size.iter_mut() {
+            if other_digest.is_none() {
+                *other_digest = digest(other, self.algorithm).ok();
+            }
+
+            if other_digest.as_ref() == Some(&file_digest) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn record(&mut self, file: &Utf8Path) {
+        if let Ok(size) = fs::metadata(file).map(|m| m.len()) {
+            self.picked
+                .entry(size)
+                .or_default()
+                .push((file.to_path_buf(), None));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use camino_tempfile_ext::prelude::*;
+
+    #[test]
+    fn test_digest() {
+        let dir = Utf8TempDir::new().unwrap();
+        dir.child("file").write_str("hello\n").unwrap();
+
+        assert_eq!(
+            "f572d396fae9206628714fb2ce00f72e94f2258f",
+            digest(&dir.path().join("file"), Algorithm::Sha1).unwrap()
+        );
+
+        assert_eq!(
+            "6bba86c7e069f56d5a10b435f1c8e49c",
+            digest(&dir.path().join("file"), Algorithm::Xxh3).unwrap()
+        );
+
+        assert!(digest(&dir.path().join("missing"), Algorithm::Sha1).is_err());
+        assert!(digest(&dir.path().join("missing"), Algorithm::Xxh3).is_err());
+    }
+
+    #[test]
+    fn test_unique_content() {
+        let dir = Utf8TempDir::new().unwrap();
+        dir.child("a").write_str("same").unwrap();
+        dir.child("b").write_str("same").unwrap();
+        dir.child("c").write_str("diff").unwrap();
+        dir.child("d").write_str("longer").unwrap();
+
+        let file = |name: &str| dir.path().join(name);
+        let mut unique = UniqueContent::new(Algorithm::default());
+
+        assert!(unique.is_unique(&file("a")));
+        unique.record(&file("a"));
+
+        assert!(!unique.is_unique(&file("b")));
+        assert!(unique.is_unique(&file("c")));
+        assert!(unique.is_unique(&file("d")));
+        assert!(unique.is_unique(&file("missing")));
+    }
+}
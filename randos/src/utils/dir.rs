@@ -1,11 +1,17 @@
 use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::HashSet;
+use std::io::{self, BufRead};
 
-pub fn pathbuf_set(files: &[String]) -> Vec<Utf8PathBuf> {
+// Sources can be given a weight, like dir:3, for --balance sources. Anything
+// which exists as given is taken to be a path, even if it has a colon in it.
+pub fn weighted_sources(sources: &[String]) -> anyhow::Result<Vec<(Utf8PathBuf, f64)>> {
     let mut ret = Vec::new();
 
-    for file in files {
+    for source in sources {
+        let (file, weight) = split_weight(source)?;
+
         match Utf8PathBuf::from(file).canonicalize_utf8() {
-            Ok(path) => ret.push(path),
+            Ok(path) => ret.push((path, weight)),
             Err(_) => {
                 eprintln!("WARNING: {} does not exist", file);
                 continue;
@@ -13,35 +19,127 @@ pub fn pathbuf_set(files: &[String]) -> Vec<Utf8PathBuf> {
         };
     }
 
-    ret
+    Ok(ret)
+}
+
+// Sources listed in a file or on stdin, one per line, or separated by NULs, as
+// find -print0 writes them. Lists can be far longer than a command line, so
+// these don't take weights.
+pub fn listed_sources(reader: impl BufRead, nul: bool) -> io::Result<Vec<Utf8PathBuf>> {
+    let mut ret = Vec::new();
+
+    for entry in reader.split(if nul { b'\0' } else { b'\n' }) {
+        let mut entry = entry?;
+
+        if !nul && entry.last() == Some(&b'\r') {
+            entry.pop();
+        }
+
+        if entry.is_empty() {
+            continue;
+        }
+
+        let Ok(file) = String::from_utf8(entry) else {
+            eprintln!("WARNING: skipping a listed source which isn't UTF-8");
+            continue;
+        };
+
+        match Utf8PathBuf::from(&file).canonicalize_utf8() {
+            Ok(path) => ret.push(path),
+            Err(_) => eprintln!("WARNING: {} does not exist", file),
+        }
+    }
+
+    Ok(ret)
+}
+
+fn split_weight(source: &str) -> anyhow::Result<(&str, f64)> {
+    if Utf8Path::new(source).exists() {
+        return Ok((source, 1.0));
+    }
+
+    match source.rsplit_once(':') {
+        Some((file, weight)) if !file.is_empty() => match weight.parse::<f64>() {
+            Ok(weight) if weight.is_finite() && weight > 0.0 => Ok((file, weight)),
+            _ => Err(anyhow::anyhow!(
+                "weight '{}' for {} must be a positive number",
+                weight,
+                file
+            )),
+        },
+        _ => Ok((source, 1.0)),
+    }
 }
 
+// Everything walk_files() would visit, sorted.
 pub fn expand_file_list(flist: &[Utf8PathBuf], recurse: bool) -> anyhow::Result<Vec<Utf8PathBuf>> {
     let mut ret: Vec<Utf8PathBuf> = Vec::new();
-    let mut dirlist: Vec<Utf8PathBuf> = Vec::new();
+    walk_files(flist, recurse, None, |file| ret.push(file));
+    ret.sort();
+    Ok(ret)
+}
 
-    for f in flist {
-        if f.is_file() {
-            ret.push(f.clone());
-        } else if f.is_dir() {
-            dirlist.push(f.clone());
-        }
+// Calls visit() with each file in flist, and, if recurse is set, every file
+// under each directory in flist. No file is visited twice, and directory order
+// varies between filesystems, so we go through everything in sorted order: a
+// seeded selection must give the same picks every time. With a max_depth, we
+// only go that far down: files directly in a directory are at depth 1.
+pub fn walk_files(
+    flist: &[Utf8PathBuf],
+    recurse: bool,
+    max_depth: Option<usize>,
+    mut visit: impl FnMut(Utf8PathBuf),
+) {
+    let mut files: Vec<&Utf8PathBuf> = flist.iter().filter(|f| f.is_file()).collect();
+    files.sort();
+    files.dedup();
+
+    for file in files.iter() {
+        visit(file.to_path_buf());
     }
 
-    if recurse {
-        for dir in expand_dir_list(&dirlist, true) {
-            if let Ok(entries) = dir.read_dir_utf8() {
-                for entry in entries.filter_map(Result::ok) {
-                    let path = entry.into_path();
-                    if path.is_file() {
-                        ret.push(path);
-                    }
-                }
+    if !recurse {
+        return;
+    }
+
+    let given: HashSet<&Utf8PathBuf> = files.into_iter().collect();
+    let dirlist: Vec<Utf8PathBuf> = flist.iter().filter(|f| f.is_dir()).cloned().collect();
+    let mut dirs = dirs_within(&dirlist, max_depth);
+    dirs.sort();
+
+    for dir in dirs {
+        let Ok(entries) = dir.read_dir_utf8() else {
+            continue;
+        };
+
+        let mut entries: Vec<Utf8PathBuf> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.into_path())
+            .collect();
+
+        entries.sort();
+
+        for path in entries {
+            if path.is_file() && !given.contains(&path) {
+                visit(path);
             }
         }
     }
+}
 
-    Ok(ret)
+// The directory file is in, relative to the deepest of roots it's under. Files
+// which aren't under any of them, like files given by name, have no directory.
+pub fn dir_under_root(file: &Utf8Path, roots: &[Utf8PathBuf]) -> Utf8PathBuf {
+    let Some(parent) = file.parent() else {
+        return Utf8PathBuf::new();
+    };
+
+    roots
+        .iter()
+        .filter_map(|root| parent.strip_prefix(root).ok())
+        .min_by_key(|relative| relative.as_str().len())
+        .map(Utf8Path::to_path_buf)
+        .unwrap_or_default()
 }
 
 pub fn expand_dir_list(dirlist: &[Utf8PathBuf], recurse: bool) -> Vec<Utf8PathBuf> {
@@ -53,29 +151,42 @@ pub fn expand_dir_list(dirlist: &[Utf8PathBuf], recurse: bool) -> Vec<Utf8PathBu
 }
 
 fn dirs_under(dirs: &[Utf8PathBuf]) -> Vec<Utf8PathBuf> {
+    dirs_within(dirs, None)
+}
+
+// The directories whose files are no deeper than max_depth.
+fn dirs_within(dirs: &[Utf8PathBuf], max_depth: Option<usize>) -> Vec<Utf8PathBuf> {
     let mut ret = Vec::new();
+    let mut seen = HashSet::new();
 
     for dir in dirs {
         let path = Utf8Path::new(&dir);
         if path.is_dir() {
-            collect_directories(path, &mut ret);
+            collect_directories(path, max_depth.unwrap_or(usize::MAX), &mut seen, &mut ret);
         }
     }
 
-    ret.into_iter().collect()
+    ret
 }
 
-fn collect_directories(dir: &Utf8Path, aggr: &mut Vec<Utf8PathBuf>) {
-    let dir_buf = dir.to_path_buf();
-
-    if !aggr.contains(&dir_buf) {
-        aggr.push(dir_buf);
+// Sources can overlap, so we remember where we've been, and don't go down the
+// same tree twice. depth is how many more levels of files we may take.
+fn collect_directories(
+    dir: &Utf8Path,
+    depth: usize,
+    seen: &mut HashSet<Utf8PathBuf>,
+    aggr: &mut Vec<Utf8PathBuf>,
+) {
+    if depth == 0 || !seen.insert(dir.to_path_buf()) {
+        return;
     }
 
+    aggr.push(dir.to_path_buf());
+
     if let Ok(entries) = dir.read_dir_utf8() {
         for entry in entries.filter_map(Result::ok) {
             if entry.path().is_dir() {
-                collect_directories(entry.path(), aggr);
+                collect_directories(entry.path(), depth - 1, seen, aggr);
             }
         }
     }
@@ -111,6 +222,148 @@ mod tests {
         assert_eq_unordered!(result_dirs, expected_dirs);
     }
 
+    #[test]
+    fn test_split_weight() {
+        let dir = fixture("dir_1");
+
+        assert_eq!((dir.as_str(), 1.0), split_weight(dir.as_str()).unwrap());
+        assert_eq!(
+            ("/no/such/dir", 2.5),
+            split_weight("/no/such/dir:2.5").unwrap()
+        );
+        assert_eq!(("/no/such/dir", 1.0), split_weight("/no/such/dir").unwrap());
+        assert!(split_weight("/no/such/dir:0").is_err());
+        assert!(split_weight("/no/such/dir:lots").is_err());
+    }
+
+    #[test]
+    fn test_weighted_sources() {
+        let sources = vec![
+            format!("{}:3", fixture("dir_1")),
+            fixture("dir_2").to_string(),
+            "/no/such/dir".to_string(),
+        ];
+
+        assert_eq!(
+            vec![(fixture("dir_1"), 3.0), (fixture("dir_2"), 1.0)],
+            weighted_sources(&sources).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_listed_sources() {
+        let list = format!(
+            "{}\n\n{}\r\n/no/such/file\n",
+            fixture("dir_1/file_1_3.png"),
+            fixture("dir_2"),
+        );
+
+        assert_eq!(
+            vec![fixture("dir_1/file_1_3.png"), fixture("dir_2")],
+            listed_sources(list.as_bytes(), false).unwrap()
+        );
+
+        let list = format!("{}\0{}\0", fixture("dir_1/file_1_3.png"), fixture("dir_2"));
+
+        assert_eq!(
+            vec![fixture("dir_1/file_1_3.png"), fixture("dir_2")],
+            listed_sources(list.as_bytes(), true).unwrap()
+        );
+
+        assert!(
+            listed_sources(&b"\xff\xfe\n"[..], false)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_dir_under_root() {
+        let roots = vec![
+            fixture("dir_1"),
+            fixture("dir_1/subdir_1_2"),
+            fixture("dir_2/file_2_1.txt"),
+        ];
+
+        assert_eq!(
+            Utf8PathBuf::from("subdir_1_1"),
+            dir_under_root(&fixture("dir_1/subdir_1_1/subdir_1_1_file_1.jpg"), &roots)
+        );
+        assert_eq!(
+            Utf8PathBuf::new(),
+            dir_under_root(&fixture("dir_1/subdir_1_2/some_file.txt"), &roots)
+        );
+        assert_eq!(
+            Utf8PathBuf::new(),
+            dir_under_root(&fixture("dir_1/file_1_3.png"), &roots)
+        );
+        assert_eq!(
+            Utf8PathBuf::new(),
+            dir_under_root(&fixture("dir_2/file_2_1.txt"), &roots)
+        );
+    }
+
+    #[test]
+    fn test_walk_files() {
+        let mut visited = Vec::new();
+
+        walk_files(
+            &[
+                fixture("dir_1/subdir_1_2"),
+                fixture("dir_1"),
+                fixture("dir_1/file_1_3.png"),
+                fixture("dir_1/file_1_3.png"),
+            ],
+            true,
+            None,
+            |file| visited.push(file),
+        );
+
+        assert_eq!(
+            vec![
+                fixture("dir_1/file_1_3.png"),
+                fixture("dir_1/file_1_1.sfx"),
+                fixture("dir_1/file_1_2.sfx"),
+                fixture("dir_1/file_1_4"),
+                fixture("dir_1/some_file.txt"),
+                fixture("dir_1/subdir_1_1/subdir_1_1_file_1.jpg"),
+                fixture("dir_1/subdir_1_2/some_file.txt"),
+                fixture("dir_1/subdir_1_2/subdir_1_1_file_2.jpg"),
+            ],
+            visited
+        );
+
+        let mut visited = Vec::new();
+        walk_files(&[fixture("dir_1")], false, None, |file| visited.push(file));
+        assert!(visited.is_empty());
+
+        let mut visited = Vec::new();
+        walk_files(&[fixture("dir_1")], true, Some(1), |file| {
+            visited.push(file)
+        });
+
+        assert_eq!(
+            vec![
+                fixture("dir_1/file_1_1.sfx"),
+                fixture("dir_1/file_1_2.sfx"),
+                fixture("dir_1/file_1_3.png"),
+                fixture("dir_1/file_1_4"),
+                fixture("dir_1/some_file.txt"),
+            ],
+            visited
+        );
+
+        let mut visited = Vec::new();
+        walk_files(
+            &[fixture("dir_1"), fixture("dir_1/subdir_1_2")],
+            true,
+            Some(1),
+            |file| visited.push(file),
+        );
+
+        assert_eq!(7, visited.len());
+    }
+
     #[test]
     fn test_expand_dir_list_no_recurse() {
         let result = expand_dir_list(&[fixture("dir_1"), fixture("dir_2")], false);
@@ -1,16 +1,71 @@
 use crate::utils::types::FilterOpts;
 use camino::Utf8Path;
+use clap::ValueEnum;
+use infer::MatcherType;
 use std::fs;
 use std::time::UNIX_EPOCH;
 
-fn file_mtime(file: &Utf8Path) -> u64 {
-    fs::metadata(file)
-        .unwrap()
-        .modified()
-        .unwrap()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
+// Kinds of file for --type. We go by the file's first few bytes, not by its
+// extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum FileType {
+    Archive,
+    Audio,
+    Document,
+    Font,
+    Image,
+    Video,
+}
+
+impl FileType {
+    fn of(file: &Utf8Path) -> Option<FileType> {
+        let kind = infer::get_from_path(file).ok()??;
+
+        // infer counts PDFs as archives, which isn't what anyone means.
+        if kind.mime_type() == "application/pdf" {
+            return Some(FileType::Document);
+        }
+
+        match kind.matcher_type() {
+            MatcherType::Archive => Some(FileType::Archive),
+            MatcherType::Audio => Some(FileType::Audio),
+            MatcherType::Doc | MatcherType::Book => Some(FileType::Document),
+            MatcherType::Font => Some(FileType::Font),
+            MatcherType::Image => Some(FileType::Image),
+            MatcherType::Video => Some(FileType::Video),
+            _ => None,
+        }
+    }
+}
+
+// Ages are a number and a unit: s, m, h, d, w or y. A bare number is days.
+pub fn parse_duration(duration: &str) -> Option<u64> {
+    let duration = duration.trim();
+    let split = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(duration.len());
+
+    let (number, unit) = duration.split_at(split);
+    let number: u64 = number.parse().ok()?;
+
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        _ => return None,
+    };
+
+    number.checked_mul(multiplier)
+}
+
+// Seconds since the epoch, or None if the file has gone, or its mtime can't be
+// read.
+pub fn file_mtime(file: &Utf8Path) -> Option<u64> {
+    let mtime = fs::metadata(file).ok()?.modified().ok()?;
+    Some(mtime.duration_since(UNIX_EPOCH).ok()?.as_secs())
 }
 
 pub fn is_candidate(file: &Utf8Path, opts: &FilterOpts) -> bool {
@@ -29,21 +84,63 @@ pub fn is_candidate(file: &Utf8Path, opts: &FilterOpts) -> bool {
         }
     }
 
+    // Patterns match the file name, unless we're asked to match the whole
+    // path. Globs with a / in them always match the whole path.
+    let Some(file_name) = file.file_name() else {
+        return false;
+    };
+
+    let subject = if opts.full_path {
+        file.as_str()
+    } else {
+        file_name
+    };
+
     if let Some(rx) = &opts.regex
-        && let Some(file_name) = file.file_name()
-        && !rx.is_match(file_name)
+        && !rx.is_match(subject)
     {
         return false;
     }
 
+    if opts.exclude.iter().any(|rx| rx.is_match(subject)) {
+        return false;
+    }
+
+    if opts.exclude_globs.iter().any(|glob| {
+        if glob.as_str().contains('/') {
+            glob.matches(file.as_str())
+        } else {
+            glob.matches(subject)
+        }
+    }) {
+        return false;
+    }
+
     if let Some(timestamp) = &opts.older
-        && file_mtime(file) > *timestamp
+        && file_mtime(file).is_none_or(|mtime| mtime > *timestamp)
     {
         return false;
     }
 
     if let Some(timestamp) = &opts.newer
-        && file_mtime(file) < *timestamp
+        && file_mtime(file).is_none_or(|mtime| mtime < *timestamp)
+    {
+        return false;
+    }
+
+    if opts.min_size.is_some() || opts.max_size.is_some() {
+        let size = fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+
+        if opts.min_size.is_some_and(|min| size < min)
+            || opts.max_size.is_some_and(|max| size > max)
+        {
+            return false;
+        }
+    }
+
+    // This reads the file, so it comes last.
+    if let Some(types) = &opts.types
+        && !FileType::of(file).is_some_and(|t| types.contains(&t))
     {
         return false;
     }
@@ -54,6 +151,7 @@ pub fn is_candidate(file: &Utf8Path, opts: &FilterOpts) -> bool {
 #[cfg(test)]
 mod test {
     use super::*;
+    use camino_tempfile_ext::prelude::*;
     use filetime::{FileTime, set_file_times};
     use regex::Regex;
     use test_utils::fixture;
@@ -73,6 +171,7 @@ mod test {
             older: Some(1737240000),
             newer: None,
             regex: None,
+            ..FilterOpts::default()
         };
 
         let good_candidates = vec!["dir_2/file_2_1.txt", "dir_2/file_2_2.txt"];
@@ -85,6 +184,7 @@ mod test {
             older: None,
             newer: Some(1737240000),
             regex: None,
+            ..FilterOpts::default()
         };
 
         let good_candidates = vec!["dir_2/file_2_3.txt"];
@@ -97,6 +197,7 @@ mod test {
             newer: Some(1737100010),
             older: Some(1737200001),
             regex: None,
+            ..FilterOpts::default()
         };
 
         let good_candidates = vec!["dir_2/file_2_2.txt"];
@@ -105,6 +206,15 @@ mod test {
         test_candidates(good_candidates, bad_candidates, &selector_opts);
     }
 
+    #[test]
+    fn test_file_mtime() {
+        let mtime = FileTime::from_unix_time(1737100000, 0);
+        set_file_times(fixture("dir_2/file_2_1.txt"), mtime, mtime).unwrap();
+
+        assert_eq!(Some(1737100000), file_mtime(&fixture("dir_2/file_2_1.txt")));
+        assert_eq!(None, file_mtime(&fixture("dir_2/no_such_file.txt")));
+    }
+
     #[test]
     fn test_is_candidates_no_filter() {
         let selector_opts = FilterOpts {
@@ -112,6 +222,7 @@ mod test {
             older: None,
             newer: None,
             regex: None,
+            ..FilterOpts::default()
         };
 
         let good_candidates = vec![
@@ -133,6 +244,7 @@ mod test {
             older: None,
             newer: None,
             regex: None,
+            ..FilterOpts::default()
         };
 
         let good_candidates = vec!["dir_1/file_1_3.png"];
@@ -154,6 +266,7 @@ mod test {
             older: None,
             newer: None,
             regex: Some(Regex::new("1_[23]").unwrap()),
+            ..FilterOpts::default()
         };
 
         let good_candidates = vec!["dir_1/file_1_3.png", "dir_1/file_1_2.sfx"];
@@ -169,6 +282,7 @@ mod test {
             older: None,
             newer: None,
             regex: Some(Regex::new("1_[23]").unwrap()),
+            ..FilterOpts::default()
         };
 
         let good_candidates = vec!["dir_1/file_1_3.png"];
@@ -189,6 +303,7 @@ mod test {
             older: None,
             newer: None,
             regex: Some(Regex::new("xyz]").unwrap()),
+            ..FilterOpts::default()
         };
 
         let good_candidates = Vec::new();
@@ -211,6 +326,7 @@ mod test {
             older: None,
             newer: None,
             regex: None,
+            ..FilterOpts::default()
         };
 
         let good_candidates = Vec::new();
@@ -226,6 +342,103 @@ mod test {
         test_candidates(good_candidates, bad_candidates, &selector_opts);
     }
 
+    #[test]
+    fn test_is_candidates_full_path_and_exclude() {
+        let selector_opts = FilterOpts {
+            regex: Some(Regex::new("subdir_1_2/").unwrap()),
+            exclude: vec![Regex::new("\\.txt$").unwrap()],
+            full_path: true,
+            ..FilterOpts::default()
+        };
+
+        let good_candidates = vec!["dir_1/subdir_1_2/subdir_1_1_file_2.jpg"];
+        let bad_candidates = vec![
+            "dir_1/subdir_1_2/some_file.txt",
+            "dir_1/subdir_1_1/subdir_1_1_file_1.jpg",
+        ];
+
+        test_candidates(good_candidates, bad_candidates, &selector_opts);
+
+        let selector_opts = FilterOpts {
+            exclude_globs: vec![
+                glob::Pattern::new("*.sfx").unwrap(),
+                glob::Pattern::new("*/subdir_1_1/*").unwrap(),
+            ],
+            ..FilterOpts::default()
+        };
+
+        let good_candidates = vec!["dir_1/file_1_3.png", "dir_1/subdir_1_2/some_file.txt"];
+        let bad_candidates = vec![
+            "dir_1/file_1_1.sfx",
+            "dir_1/subdir_1_1/subdir_1_1_file_1.jpg",
+        ];
+
+        test_candidates(good_candidates, bad_candidates, &selector_opts);
+    }
+
+    #[test]
+    fn test_is_candidates_size() {
+        let selector_opts = FilterOpts {
+            min_size: Some(1),
+            max_size: Some(20),
+            ..FilterOpts::default()
+        };
+
+        let good_candidates = vec!["dir_1/file_1_3.png"];
+        let bad_candidates = vec!["dir_1/subdir_1_1"];
+
+        test_candidates(good_candidates, bad_candidates, &selector_opts);
+
+        let selector_opts = FilterOpts {
+            min_size: Some(1000),
+            ..FilterOpts::default()
+        };
+
+        test_candidates(Vec::new(), vec!["dir_1/file_1_3.png"], &selector_opts);
+    }
+
+    #[test]
+    fn test_is_candidates_type() {
+        let dir = Utf8TempDir::new().unwrap();
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let pdf = b"%PDF-1.7\n";
+
+        dir.child("image.png").write_binary(png).unwrap();
+        dir.child("image.txt").write_binary(png).unwrap();
+        dir.child("paper.png").write_binary(pdf).unwrap();
+        dir.child("notes.txt").write_str("just text").unwrap();
+
+        let opts = FilterOpts {
+            types: Some(vec![FileType::Image]),
+            ..FilterOpts::default()
+        };
+
+        assert!(is_candidate(&dir.path().join("image.png"), &opts));
+        assert!(is_candidate(&dir.path().join("image.txt"), &opts));
+        assert!(!is_candidate(&dir.path().join("paper.png"), &opts));
+        assert!(!is_candidate(&dir.path().join("notes.txt"), &opts));
+
+        let opts = FilterOpts {
+            types: Some(vec![FileType::Audio, FileType::Document]),
+            ..FilterOpts::default()
+        };
+
+        assert!(is_candidate(&dir.path().join("paper.png"), &opts));
+        assert!(!is_candidate(&dir.path().join("image.png"), &opts));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(Some(3 * 86400), parse_duration("3"));
+        assert_eq!(Some(3 * 86400), parse_duration("3d"));
+        assert_eq!(Some(6 * 3600), parse_duration("6h"));
+        assert_eq!(Some(2 * 7 * 86400), parse_duration("2w"));
+        assert_eq!(Some(90), parse_duration("90s"));
+        assert_eq!(Some(15 * 60), parse_duration("15m"));
+        assert_eq!(None, parse_duration("2 fortnights"));
+        assert_eq!(None, parse_duration("h"));
+    }
+
     fn test_candidates(good: Vec<&str>, bad: Vec<&str>, opts: &FilterOpts) {
         good.iter()
             .for_each(|c| assert!(is_candidate(&fixture(c), opts), "{} WAS BAD", c));
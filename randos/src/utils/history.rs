@@ -0,0 +1,103 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Write};
+use tempfile::NamedTempFile;
+
+// Remembers which files have been picked, one canonical path per line, so
+// --no-repeat can keep away from them. Once everything has been picked, a new
+// cycle starts and the history is forgotten.
+
+pub struct History {
+    path: Utf8PathBuf,
+    seen: BTreeSet<Utf8PathBuf>,
+}
+
+impl History {
+    // A history file which doesn't exist yet is an empty history.
+    pub fn load(path: &Utf8Path) -> io::Result<History> {
+        let seen = match fs::read_to_string(path) {
+            Ok(contents) => contents
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(Utf8PathBuf::from)
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeSet::new(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(History {
+            path: path.to_path_buf(),
+            seen,
+        })
+    }
+
+    pub fn contains(&self, file: &Utf8Path) -> bool {
+        self.seen.contains(&key(file))
+    }
+
+    pub fn record(&mut self, file: &Utf8Path) {
+        self.seen.insert(key(file));
+    }
+
+    pub fn new_cycle(&mut self) {
+        self.seen.clear();
+    }
+
+    // Writes the whole history to a temporary file and renames it into place,
+    // so an interrupted run can't leave half a history.
+    pub fn save(&self) -> io::Result<()> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_str().is_empty() => dir,
+            _ => Utf8Path::new("."),
+        };
+
+        fs::create_dir_all(dir)?;
+        let mut tmp = NamedTempFile::new_in(dir)?;
+
+        for file in self.seen.iter() {
+            writeln!(tmp, "{}", file)?;
+        }
+
+        tmp.persist(&self.path)?;
+        Ok(())
+    }
+}
+
+// Files are known by their canonical path, so the same file reached through a
+// different source, or a link, is still the same file.
+fn key(file: &Utf8Path) -> Utf8PathBuf {
+    file.canonicalize_utf8()
+        .unwrap_or_else(|_| file.to_path_buf())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use camino_tempfile_ext::prelude::*;
+    use test_utils::fixture;
+
+    #[test]
+    fn test_history() {
+        let dir = Utf8TempDir::new().unwrap();
+        let path = dir.path().join("state/history");
+        let mut history = History::load(&path).unwrap();
+
+        assert!(!history.contains(&fixture("dir_1/file_1_1.sfx")));
+
+        history.record(&fixture("dir_1/file_1_1.sfx"));
+        history.record(&fixture("dir_1/subdir_1_1/../file_1_2.sfx"));
+        history.save().unwrap();
+
+        let mut history = History::load(&path).unwrap();
+
+        assert!(history.contains(&fixture("dir_1/file_1_1.sfx")));
+        assert!(history.contains(&fixture("dir_1/file_1_2.sfx")));
+        assert!(!history.contains(&fixture("dir_1/file_1_3.png")));
+
+        history.new_cycle();
+        history.save().unwrap();
+
+        assert_eq!("", fs::read_to_string(&path).unwrap());
+    }
+}
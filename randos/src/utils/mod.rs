@@ -1,5 +1,13 @@
 pub mod actions;
+pub mod budget;
+pub mod content;
 pub mod dir;
 pub mod filter;
+pub mod history;
 pub mod namer;
+pub mod progress;
+pub mod record;
+pub mod sample;
+pub mod sync;
+pub mod template;
 pub mod types;
@@ -1,22 +1,164 @@
-use camino::Utf8PathBuf;
-use rand::RngExt;
+use crate::utils::content::{self, Algorithm};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::ValueEnum;
 use rand::distr::Alphanumeric;
+use rand::{Rng, RngExt};
+use std::collections::HashSet;
 
-pub fn name_from(path: &Utf8PathBuf, seq_no: usize, scheme: &Option<String>) -> Option<String> {
-    if let Some(basename) = path.file_name() {
-        let ret = match scheme {
-            Some(s) => match s.as_str() {
-                "hash" => hash(path),
-                "random" => random(path),
-                "sequential" => sequential(path, seq_no),
-                "expand" => expand(path),
-                _ => plain(basename),
+// What to do when a pick's name is already taken in the destination, and the
+// scheme can't give it another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Collision {
+    /// Pick another file instead
+    Skip,
+    /// Add a number to the name, like cover-1.jpg
+    Suffix,
+    /// Name the file with the expand scheme
+    Expand,
+}
+
+// The destination names we can't use: this run's picks, and anything already
+// there. With sync, its own files which are about to be removed don't count.
+#[derive(Default)]
+pub struct InUse {
+    pub taken: HashSet<Utf8PathBuf>,
+    pub freed: HashSet<Utf8PathBuf>,
+}
+
+impl InUse {
+    pub fn contains(&self, dest: &Utf8Path) -> bool {
+        self.taken.contains(dest) || (dest.symlink_metadata().is_ok() && !self.freed.contains(dest))
+    }
+}
+
+// How picks are named in the destination. Each scheme has a Namer, so adding
+// a scheme means adding a variant here and an arm in namer().
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Scheme {
+    /// The source's own name
+    #[default]
+    Plain,
+    /// A SHA1 of the source's full path
+    Hash,
+    /// A hash of the source's contents, made with --hash
+    ContentHash,
+    /// 32 random letters and digits
+    Random,
+    /// 00000001 upwards
+    #[value(alias = "seq")]
+    Sequential,
+    /// The source's full path, with / replaced by -
+    Expand,
+}
+
+impl Scheme {
+    // algorithm is how content-hash digests file contents.
+    pub fn namer(self, algorithm: Algorithm) -> &'static dyn Namer {
+        match self {
+            Scheme::Plain => &PlainNamer,
+            Scheme::Hash => &HashNamer,
+            Scheme::ContentHash => match algorithm {
+                Algorithm::Xxh3 => &ContentHashNamer(Algorithm::Xxh3),
+                Algorithm::Sha1 => &ContentHashNamer(Algorithm::Sha1),
             },
-            None => plain(basename),
-        };
-        Some(ret)
-    } else {
-        None
+            Scheme::Random => &RandomNamer,
+            Scheme::Sequential => &SequentialNamer,
+            Scheme::Expand => &ExpandNamer,
+        }
+    }
+}
+
+pub trait Namer: Sync {
+    // None if the file can't be named, so it's skipped.
+    fn name(&self, path: &Utf8Path, seq_no: usize, rng: &mut dyn Rng) -> Option<String>;
+}
+
+struct PlainNamer;
+struct HashNamer;
+struct ContentHashNamer(Algorithm);
+struct RandomNamer;
+struct SequentialNamer;
+struct ExpandNamer;
+
+impl Namer for PlainNamer {
+    fn name(&self, path: &Utf8Path, _: usize, _: &mut dyn Rng) -> Option<String> {
+        path.file_name().map(plain)
+    }
+}
+
+impl Namer for HashNamer {
+    fn name(&self, path: &Utf8Path, _: usize, _: &mut dyn Rng) -> Option<String> {
+        Some(hash(path))
+    }
+}
+
+// Files which can't be read can't be named, so they're skipped.
+impl Namer for ContentHashNamer {
+    fn name(&self, path: &Utf8Path, _: usize, _: &mut dyn Rng) -> Option<String> {
+        let extension = path.extension().unwrap_or("");
+        let digest = content::digest(path, self.0).ok()?;
+        Some(format!("{}.{}", digest, extension))
+    }
+}
+
+impl Namer for RandomNamer {
+    fn name(&self, path: &Utf8Path, _: usize, rng: &mut dyn Rng) -> Option<String> {
+        Some(random(path, rng))
+    }
+}
+
+impl Namer for SequentialNamer {
+    fn name(&self, path: &Utf8Path, seq_no: usize, _: &mut dyn Rng) -> Option<String> {
+        Some(sequential(path, seq_no))
+    }
+}
+
+impl Namer for ExpandNamer {
+    fn name(&self, path: &Utf8Path, _: usize, _: &mut dyn Rng) -> Option<String> {
+        Some(expand(path))
+    }
+}
+
+// Names path with one of the schemes, hashing contents with the default --hash.
+pub fn name_from(
+    path: &Utf8PathBuf,
+    seq_no: usize,
+    scheme: Scheme,
+    rng: &mut dyn Rng,
+) -> Option<String> {
+    name_with(path, seq_no, scheme.namer(Algorithm::default()), rng)
+}
+
+// Like name_from(), with any Namer, like a --template.
+pub fn name_with(
+    path: &Utf8Path,
+    seq_no: usize,
+    namer: &dyn Namer,
+    rng: &mut dyn Rng,
+) -> Option<String> {
+    path.file_name()?;
+    namer.name(path, seq_no, rng)
+}
+
+// Another name for path, whose name is taken, or None if it should be skipped.
+pub fn avoid_collision(
+    name: &str,
+    path: &Utf8Path,
+    collision: Collision,
+    taken: impl Fn(&str) -> bool,
+) -> Option<String> {
+    match collision {
+        Collision::Skip => None,
+        Collision::Suffix => (1..).map(|n| with_suffix(name, n)).find(|n| !taken(n)),
+        Collision::Expand => Some(expand(path)).filter(|n| !taken(n)),
+    }
+}
+
+// cover.jpg becomes cover-1.jpg. A leading dot doesn't start an extension.
+fn with_suffix(name: &str, n: usize) -> String {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => format!("{}-{}{}", &name[..dot], n, &name[dot..]),
+        _ => format!("{}-{}", name, n),
     }
 }
 
@@ -24,7 +166,7 @@ fn plain(name: &str) -> String {
     name.to_string()
 }
 
-fn hash(path: &Utf8PathBuf) -> String {
+fn hash(path: &Utf8Path) -> String {
     let extension = path.extension().unwrap_or("");
     let path_string = path.to_string();
     let mut hasher = sha1_smol::Sha1::new();
@@ -32,10 +174,10 @@ fn hash(path: &Utf8PathBuf) -> String {
     format!("{}.{}", hasher.digest(), extension)
 }
 
-fn random(path: &Utf8PathBuf) -> String {
+fn random(path: &Utf8Path, rng: &mut dyn Rng) -> String {
     let extension = path.extension().unwrap_or("");
 
-    let stem: String = rand::rng()
+    let stem: String = rng
         .sample_iter(&Alphanumeric)
         .take(32)
         .map(char::from)
@@ -44,12 +186,12 @@ fn random(path: &Utf8PathBuf) -> String {
     format!("{}.{}", stem, extension)
 }
 
-fn sequential(path: &Utf8PathBuf, seq_no: usize) -> String {
+fn sequential(path: &Utf8Path, seq_no: usize) -> String {
     let extension = path.extension().unwrap_or("");
     format!("{:08}.{}", seq_no, extension)
 }
 
-fn expand(path: &Utf8PathBuf) -> String {
+fn expand(path: &Utf8Path) -> String {
     path.to_string()
         .replace('/', "-")
         .trim_start_matches('-')
@@ -59,36 +201,113 @@ fn expand(path: &Utf8PathBuf) -> String {
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
     use test_utils::fixture;
 
     #[test]
     fn test_name_from() {
+        let mut rng = StdRng::seed_from_u64(1);
+
         assert_eq!(
             "file_1_3.png".to_string(),
-            name_from(&fixture("dir_1/file_1_3.png"), 1, &Some("plain".into())).unwrap()
+            name_from(&fixture("dir_1/file_1_3.png"), 1, Scheme::Plain, &mut rng).unwrap()
         );
 
-        let rand = name_from(&fixture("dir_1/file_1_3.png"), 1, &Some("random".into())).unwrap();
+        let rand = name_from(&fixture("dir_1/file_1_3.png"), 1, Scheme::Random, &mut rng).unwrap();
         assert!(rand.ends_with(".png"));
         assert_eq!(36, rand.len());
 
-        let hashed = name_from(&fixture("dir_1/file_1_3.png"), 1, &Some("hash".into())).unwrap();
+        let hashed = name_from(&fixture("dir_1/file_1_3.png"), 1, Scheme::Hash, &mut rng).unwrap();
         assert!(hashed.ends_with(".png"));
         assert_eq!(44, hashed.len());
 
+        assert_eq!(
+            "6537488a4569d7ea329b8bb44cd0dd0698889ccc.png".to_string(),
+            name_with(
+                &fixture("dir_1/file_1_3.png"),
+                1,
+                Scheme::ContentHash.namer(Algorithm::Sha1),
+                &mut rng
+            )
+            .unwrap()
+        );
+
+        let content_hashed = name_from(
+            &fixture("dir_1/file_1_3.png"),
+            1,
+            Scheme::ContentHash,
+            &mut rng,
+        )
+        .unwrap();
+        assert!(content_hashed.ends_with(".png"));
+        assert_eq!(36, content_hashed.len());
+
+        assert!(
+            name_from(
+                &fixture("dir_1/no_such_file.png"),
+                1,
+                Scheme::ContentHash,
+                &mut rng
+            )
+            .is_none()
+        );
+
         assert_eq!(
             "00000015.png".to_string(),
             name_from(
                 &fixture("dir_1/file_1_3.png"),
                 15,
-                &Some("sequential".into())
+                Scheme::Sequential,
+                &mut rng
             )
             .unwrap()
         );
 
-        let expand = name_from(&fixture("dir_1/file_1_3.png"), 1, &Some("expand".into())).unwrap();
+        let expand =
+            name_from(&fixture("dir_1/file_1_3.png"), 1, Scheme::Expand, &mut rng).unwrap();
         assert!(expand.ends_with(".png"));
         assert!(!expand.starts_with('-'));
         assert!(!expand.contains('/'));
     }
+
+    #[test]
+    fn test_random_is_seeded() {
+        let file = fixture("dir_1/file_1_3.png");
+        let scheme = Scheme::Random;
+
+        let first = name_from(&file, 1, scheme, &mut StdRng::seed_from_u64(7));
+        let second = name_from(&file, 1, scheme, &mut StdRng::seed_from_u64(7));
+        let other = name_from(&file, 1, scheme, &mut StdRng::seed_from_u64(8));
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn test_avoid_collision() {
+        let path = Utf8Path::new("/music/album/cover.jpg");
+        let taken = |name: &str| ["cover.jpg", "cover-1.jpg"].contains(&name);
+
+        assert_eq!(
+            None,
+            avoid_collision("cover.jpg", path, Collision::Skip, taken)
+        );
+        assert_eq!(
+            Some("cover-2.jpg".to_string()),
+            avoid_collision("cover.jpg", path, Collision::Suffix, taken)
+        );
+        assert_eq!(
+            Some("music-album-cover.jpg".to_string()),
+            avoid_collision("cover.jpg", path, Collision::Expand, taken)
+        );
+        assert_eq!(
+            None,
+            avoid_collision("cover.jpg", path, Collision::Expand, |_| true)
+        );
+
+        assert_eq!("README-3", with_suffix("README", 3));
+        assert_eq!(".hidden-1", with_suffix(".hidden", 1));
+        assert_eq!("a.tar-1.gz", with_suffix("a.tar.gz", 1));
+    }
 }
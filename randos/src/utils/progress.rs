@@ -0,0 +1,127 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+// A one-line progress bar on stderr, for --progress, redrawn in place.
+
+const WIDTH: usize = 30;
+
+pub struct Progress {
+    files: usize,
+    bytes: u64,
+    start: Instant,
+}
+
+impl Progress {
+    pub fn new(files: usize, bytes: u64) -> Progress {
+        Progress {
+            files,
+            bytes,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn draw(&self, files_done: usize, bytes_done: u64) {
+        let line = self.line(files_done, bytes_done, self.start.elapsed());
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r{}", line);
+        let _ = stderr.flush();
+    }
+
+    pub fn finish(&self, files_done: usize, bytes_done: u64) {
+        self.draw(files_done, bytes_done);
+        eprintln!();
+    }
+
+    fn line(&self, files_done: usize, bytes_done: u64, elapsed: Duration) -> String {
+        let share = match self.bytes {
+            0 if self.files == 0 => 1.0,
+            0 => files_done as f64 / self.files as f64,
+            _ => bytes_done as f64 / self.bytes as f64,
+        }
+        .min(1.0);
+
+        let filled = (share * WIDTH as f64) as usize;
+        let rate = bytes_done as f64 / elapsed.as_secs_f64().max(0.001);
+
+        let eta = if bytes_done == 0 || bytes_done >= self.bytes {
+            "-".to_string()
+        } else {
+            duration(Duration::from_secs_f64(
+                (self.bytes - bytes_done) as f64 / rate,
+            ))
+        };
+
+        format!(
+            "[{}{}] {}/{} files, {} of {}, {}/s, ETA {}",
+            "#".repeat(filled),
+            " ".repeat(WIDTH - filled),
+            files_done,
+            self.files,
+            bytes(bytes_done),
+            bytes(self.bytes),
+            bytes(rate as u64),
+            eta
+        )
+    }
+}
+
+fn bytes(n: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = n as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{} B", n),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
+    }
+}
+
+fn duration(d: Duration) -> String {
+    let secs = d.as_secs();
+
+    match secs {
+        0..3600 => format!("{}:{:02}", secs / 60, secs % 60),
+        _ => format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        let progress = Progress::new(4, 4 * 1024 * 1024);
+
+        assert_eq!(
+            "[###############               ] 2/4 files, 2.0 MiB of 4.0 MiB, 1.0 MiB/s, ETA 0:02",
+            progress.line(2, 2 * 1024 * 1024, Duration::from_secs(2))
+        );
+
+        assert_eq!(
+            "[                              ] 0/4 files, 0 B of 4.0 MiB, 0 B/s, ETA -",
+            progress.line(0, 0, Duration::from_secs(0))
+        );
+
+        let links = Progress::new(4, 0);
+        assert!(
+            links
+                .line(1, 0, Duration::from_secs(1))
+                .starts_with("[#######        ")
+        );
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!("1023 B", bytes(1023));
+        assert_eq!("1.5 KiB", bytes(1536));
+        assert_eq!("2.0 TiB", bytes(2 << 40));
+        assert_eq!("1:05", duration(Duration::from_secs(65)));
+        assert_eq!("2:00:05", duration(Duration::from_secs(7205)));
+    }
+}
@@ -0,0 +1,214 @@
+use crate::utils::actions::{self, Action, Verify};
+use crate::utils::types::ActionOpts;
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::os::unix::fs::MetadataExt;
+
+// --record writes down everything randos did, so a run can be looked over, or
+// put back with randos undo. Files ending .csv get CSV, anything else gets JSON
+// Lines. Each record is written as soon as its action is done, so even an
+// interrupted run can be undone.
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub source: Utf8PathBuf,
+    pub dest: Utf8PathBuf,
+    pub action: Action,
+    pub size: u64,
+    // Seconds since the epoch
+    pub mtime: u64,
+    pub seed: u64,
+}
+
+pub enum Writer {
+    Jsonl(File),
+    Csv(Box<csv::Writer<File>>),
+}
+
+fn is_csv(path: &Utf8Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+}
+
+impl Writer {
+    pub fn create(path: &Utf8Path) -> io::Result<Writer> {
+        let file = File::create(path)?;
+
+        if is_csv(path) {
+            Ok(Writer::Csv(Box::new(csv::Writer::from_writer(file))))
+        } else {
+            Ok(Writer::Jsonl(file))
+        }
+    }
+
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        match self {
+            Writer::Jsonl(file) => {
+                let mut line = serde_json::to_string(record)?;
+                line.push('\n');
+                file.write_all(line.as_bytes())
+            }
+            Writer::Csv(writer) => {
+                writer.serialize(record).map_err(io::Error::other)?;
+                writer.flush()
+            }
+        }
+    }
+}
+
+pub fn read(path: &Utf8Path) -> io::Result<Vec<Record>> {
+    if is_csv(path) {
+        csv::Reader::from_path(path)
+            .map_err(io::Error::other)?
+            .deserialize()
+            .map(|record| record.map_err(io::Error::other))
+            .collect()
+    } else {
+        fs::read_to_string(path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(io::Error::from))
+            .collect()
+    }
+}
+
+// Puts back what one record did: links and copies are removed, and moved
+// files are moved back. Anything which isn't as we left it is left alone.
+pub fn undo(record: &Record, verbose: bool, noop: bool) -> io::Result<()> {
+    let dest = &record.dest;
+    let metadata = dest.symlink_metadata()?;
+
+    if record.action.handler().moves() {
+        // rename() won't cross filesystems.
+        let action = if same_filesystem(dest, &record.source) {
+            Action::Mv
+        } else {
+            Action::Mvx
+        };
+
+        let opts = ActionOpts {
+            action,
+            auto_reflink: false,
+            noop,
+            relative_links: false,
+            verbose,
+            verify: Verify::Size,
+        };
+
+        return actions::run(dest, &record.source, &opts);
+    }
+
+    if !record.action.handler().made(&metadata, record.size) {
+        return Err(io::Error::other(format!(
+            "{} has changed: not removing it",
+            dest
+        )));
+    }
+
+    if verbose || noop {
+        println!("rm: {}", dest);
+    }
+
+    if noop {
+        return Ok(());
+    }
+
+    fs::remove_file(dest)
+}
+
+// True if file is on the same filesystem as where path would be, going by
+// the nearest directory above path which still exists.
+fn same_filesystem(file: &Utf8Path, path: &Utf8Path) -> bool {
+    let Ok(metadata) = file.metadata() else {
+        return false;
+    };
+
+    path.ancestors()
+        .skip(1)
+        .find_map(|dir| dir.metadata().ok())
+        .is_some_and(|dir| dir.dev() == metadata.dev())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use camino_tempfile_ext::prelude::*;
+
+    fn record(dir: &Utf8TempDir, action: Action, size: u64) -> Record {
+        Record {
+            source: dir.path().join("src/file.jpg"),
+            dest: dir.path().join("dest/file.jpg"),
+            action,
+            size,
+            mtime: 1_700_000_000,
+            seed: 42,
+        }
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let dir = Utf8TempDir::new().unwrap();
+        let records = vec![record(&dir, Action::Cp, 10), record(&dir, Action::Ln, 0)];
+
+        for name in ["run.jsonl", "run.csv"] {
+            let path = dir.path().join(name);
+            let mut writer = Writer::create(&path).unwrap();
+
+            for record in records.iter() {
+                writer.write(record).unwrap();
+            }
+
+            assert_eq!(records, read(&path).unwrap());
+        }
+
+        let json = fs::read_to_string(dir.path().join("run.jsonl")).unwrap();
+        assert_eq!(2, json.lines().count());
+        assert!(json.starts_with("{\"source\":"));
+
+        let csv = fs::read_to_string(dir.path().join("run.csv")).unwrap();
+        assert!(csv.starts_with("source,dest,action,size,mtime,seed\n"));
+    }
+
+    #[test]
+    fn test_undo() {
+        let dir = Utf8TempDir::new().unwrap();
+        dir.child("src/file.jpg").write_str("content").unwrap();
+        dir.child("dest/file.jpg").write_str("content").unwrap();
+
+        // Not the copy we made, so it stays.
+        assert!(undo(&record(&dir, Action::Cp, 3), false, false).is_err());
+        assert!(undo(&record(&dir, Action::Ln, 7), false, false).is_err());
+        assert!(dir.path().join("dest/file.jpg").exists());
+
+        assert!(undo(&record(&dir, Action::Cp, 7), false, true).is_ok());
+        assert!(dir.path().join("dest/file.jpg").exists());
+
+        assert!(undo(&record(&dir, Action::Cp, 7), false, false).is_ok());
+        assert!(!dir.path().join("dest/file.jpg").exists());
+
+        std::os::unix::fs::symlink(
+            dir.path().join("src/file.jpg"),
+            dir.path().join("dest/file.jpg"),
+        )
+        .unwrap();
+
+        assert!(undo(&record(&dir, Action::Ln, 7), false, false).is_ok());
+        assert!(dir.path().join("dest/file.jpg").symlink_metadata().is_err());
+
+        fs::rename(
+            dir.path().join("src/file.jpg"),
+            dir.path().join("dest/file.jpg"),
+        )
+        .unwrap();
+        fs::remove_dir(dir.path().join("src")).unwrap();
+
+        assert!(undo(&record(&dir, Action::Mv, 7), false, false).is_ok());
+        assert_eq!(
+            "content",
+            fs::read_to_string(dir.path().join("src/file.jpg")).unwrap()
+        );
+        assert!(undo(&record(&dir, Action::Mv, 7), false, false).is_err());
+    }
+}
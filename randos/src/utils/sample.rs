@@ -0,0 +1,388 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::ValueEnum;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngExt};
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::SystemTime;
+
+// Candidates are put into groups ("strata"), and picks are shared between the
+// groups in proportion to their weights, rather than being spread uniformly
+// over every file. With Balance::Files there's a single group, which is the
+// plain uniform selection.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Balance {
+    /// Every file has the same chance
+    Files,
+    /// Every source has the same share, or a share in proportion to its weight
+    Sources,
+    /// Every file extension has the same share
+    Ext,
+    /// Every age bucket has the same share
+    Age,
+}
+
+pub type Stratum = (f64, Vec<Utf8PathBuf>);
+
+pub struct Sampler {
+    // weight, credit, shuffled candidates
+    strata: Vec<(f64, f64, Vec<Utf8PathBuf>)>,
+}
+
+impl Sampler {
+    pub fn new<R: Rng + ?Sized>(strata: Vec<Stratum>, rng: &mut R) -> Sampler {
+        let mut strata: Vec<(f64, f64, Vec<Utf8PathBuf>)> = strata
+            .into_iter()
+            .filter(|(weight, candidates)| *weight > 0.0 && !candidates.is_empty())
+            .map(|(weight, mut candidates)| {
+                candidates.shuffle(rng);
+                (weight, 0.0, candidates)
+            })
+            .collect();
+
+        // Otherwise, with fewer picks than strata, the first strata would
+        // always win.
+        if strata.len() > 1 {
+            strata.shuffle(rng);
+        }
+
+        Sampler { strata }
+    }
+
+    // The next candidate which accept() likes. Strata take turns by smooth
+    // weighted round-robin, so their shares stay in proportion however many
+    // picks are made. When a stratum runs dry, the others fill in for it.
+    pub fn next_accepted(
+        &mut self,
+        mut accept: impl FnMut(&Utf8PathBuf) -> bool,
+    ) -> Option<Utf8PathBuf> {
+        loop {
+            self.strata
+                .retain(|(_weight, _credit, candidates)| !candidates.is_empty());
+
+            if self.strata.is_empty() {
+                return None;
+            }
+
+            let total: f64 = self.strata.iter().map(|(weight, _, _)| weight).sum();
+            let mut chosen = 0;
+            let mut best = f64::MIN;
+
+            for (i, (weight, credit, _candidates)) in self.strata.iter_mut().enumerate() {
+                *credit += *weight;
+
+                if *credit > best {
+                    chosen = i;
+                    best = *credit;
+                }
+            }
+
+            self.strata[chosen].1 -= total;
+
+            while let Some(candidate) = self.strata[chosen].2.pop() {
+                if accept(&candidate) {
+                    return Some(candidate);
+                }
+            }
+        }
+    }
+}
+
+// Keeps a uniform random sample of at most size of the files offered to it,
+// however many there are, by reservoir sampling.
+struct Reservoir {
+    size: usize,
+    offered: usize,
+    files: Vec<Utf8PathBuf>,
+}
+
+impl Reservoir {
+    fn offer<R: Rng + ?Sized>(&mut self, file: Utf8PathBuf, rng: &mut R) {
+        self.offered += 1;
+
+        if self.files.len() < self.size {
+            self.files.push(file);
+        } else {
+            let i = rng.random_range(0..self.offered);
+
+            if i < self.size {
+                self.files[i] = file;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Key {
+    All,
+    Source(usize),
+    Listed,
+    Ext(String),
+    Age(u64),
+}
+
+// Sorts candidates into weighted strata as they're found. Given a size, each
+// stratum only keeps a random sample that big, so memory use depends on how
+// many files we want, not on how many there are. A stratum can never give more
+// than size picks, so that's all it needs, unless some picks are turned down.
+pub struct Stratifier<'a> {
+    balance: Balance,
+    sources: &'a [(Utf8PathBuf, f64)],
+    now: SystemTime,
+    bucket_secs: u64,
+    size: usize,
+    strata: BTreeMap<Key, Reservoir>,
+}
+
+impl<'a> Stratifier<'a> {
+    // sources are the source roots given on the command line, with their
+    // weights.
+    pub fn new(
+        balance: Balance,
+        sources: &'a [(Utf8PathBuf, f64)],
+        bucket_days: u64,
+        size: Option<usize>,
+    ) -> Stratifier<'a> {
+        Stratifier {
+            balance,
+            sources,
+            now: SystemTime::now(),
+            bucket_secs: bucket_days.max(1) * 24 * 60 * 60,
+            size: size.unwrap_or(usize::MAX),
+            strata: BTreeMap::new(),
+        }
+    }
+
+    pub fn offer<R: Rng + ?Sized>(&mut self, file: Utf8PathBuf, rng: &mut R) {
+        let key = self.key(&file);
+
+        self.strata
+            .entry(key)
+            .or_insert_with(|| Reservoir {
+                size: self.size,
+                offered: 0,
+                files: Vec::new(),
+            })
+            .offer(file, rng);
+    }
+
+    // True if any stratum had to leave files out.
+    pub fn is_truncated(&self) -> bool {
+        self.strata.values().any(|r| r.offered > r.files.len())
+    }
+
+    pub fn into_strata(self) -> Vec<Stratum> {
+        let sources = self.sources;
+
+        self.strata
+            .into_iter()
+            .map(|(key, reservoir)| {
+                let weight = match key {
+                    Key::Source(i) => sources[i].1,
+                    _ => 1.0,
+                };
+
+                (weight, reservoir.files)
+            })
+            .collect()
+    }
+
+    // Files from --from lists which aren't under any source share a stratum.
+    fn key(&self, file: &Utf8Path) -> Key {
+        match self.balance {
+            Balance::Files => Key::All,
+            Balance::Sources => source_of(file, self.sources).map_or(Key::Listed, Key::Source),
+            Balance::Ext => Key::Ext(file.extension().unwrap_or_default().to_lowercase()),
+            Balance::Age => Key::Age(match fs::metadata(file).and_then(|m| m.modified()) {
+                Ok(mtime) => {
+                    self.now.duration_since(mtime).unwrap_or_default().as_secs() / self.bucket_secs
+                }
+                Err(_) => u64::MAX,
+            }),
+        }
+    }
+}
+
+// The index of the source a file came from: the longest source root it is
+// under.
+fn source_of(file: &Utf8Path, sources: &[(Utf8PathBuf, f64)]) -> Option<usize> {
+    sources
+        .iter()
+        .enumerate()
+        .filter(|(_i, (root, _weight))| file.starts_with(root))
+        .max_by_key(|(_i, (root, _weight))| root.as_str().len())
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use test_utils::fixture;
+
+    fn files(prefix: &str, count: usize) -> Vec<Utf8PathBuf> {
+        (0..count)
+            .map(|i| Utf8PathBuf::from(format!("{}/{}.jpg", prefix, i)))
+            .collect()
+    }
+
+    fn take(sampler: &mut Sampler, count: usize) -> Vec<Utf8PathBuf> {
+        (0..count)
+            .filter_map(|_| sampler.next_accepted(|_| true))
+            .collect()
+    }
+
+    #[test]
+    fn test_sampler_shares() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut sampler = Sampler::new(
+            vec![(1.0, files("/big", 1000)), (1.0, files("/small", 50))],
+            &mut rng,
+        );
+
+        let picks = take(&mut sampler, 20);
+        assert_eq!(10, picks.iter().filter(|f| f.starts_with("/small")).count());
+
+        let mut sampler = Sampler::new(
+            vec![(3.0, files("/a", 100)), (1.0, files("/b", 100))],
+            &mut rng,
+        );
+
+        let picks = take(&mut sampler, 40);
+        assert_eq!(30, picks.iter().filter(|f| f.starts_with("/a")).count());
+    }
+
+    #[test]
+    fn test_sampler_runs_dry() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut sampler = Sampler::new(
+            vec![
+                (1.0, files("/a", 100)),
+                (1.0, files("/b", 3)),
+                (0.0, files("/c", 5)),
+            ],
+            &mut rng,
+        );
+
+        let picks = take(&mut sampler, 20);
+        assert_eq!(20, picks.len());
+        assert_eq!(3, picks.iter().filter(|f| f.starts_with("/b")).count());
+        assert!(picks.iter().all(|f| !f.starts_with("/c")));
+    }
+
+    #[test]
+    fn test_sampler_accept() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut sampler = Sampler::new(vec![(1.0, files("/a", 10))], &mut rng);
+        let odd = |f: &Utf8PathBuf| f.file_stem().unwrap().parse::<usize>().unwrap() % 2 == 1;
+
+        let picks: Vec<Utf8PathBuf> = std::iter::from_fn(|| sampler.next_accepted(odd)).collect();
+
+        assert_eq!(5, picks.len());
+        assert!(picks.iter().all(odd));
+    }
+
+    fn stratify(balance: Balance, sources: &[(Utf8PathBuf, f64)]) -> Vec<Stratum> {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut stratifier = Stratifier::new(balance, sources, 30, None);
+
+        for file in [
+            fixture("dir_1/file_1_1.sfx"),
+            fixture("dir_1/file_1_3.png"),
+            fixture("dir_1/subdir_1_2/some_file.txt"),
+            fixture("dir_2/file_2_1.txt"),
+        ] {
+            stratifier.offer(file, &mut rng);
+        }
+
+        assert!(!stratifier.is_truncated());
+        stratifier.into_strata()
+    }
+
+    #[test]
+    fn test_stratifier() {
+        let sources = vec![
+            (fixture("dir_1"), 1.0),
+            (fixture("dir_1/subdir_1_2"), 2.0),
+            (fixture("dir_2"), 3.0),
+        ];
+
+        assert_eq!(
+            vec![
+                (
+                    1.0,
+                    vec![fixture("dir_1/file_1_1.sfx"), fixture("dir_1/file_1_3.png")]
+                ),
+                (2.0, vec![fixture("dir_1/subdir_1_2/some_file.txt")]),
+                (3.0, vec![fixture("dir_2/file_2_1.txt")]),
+            ],
+            stratify(Balance::Sources, &sources)
+        );
+
+        assert_eq!(
+            vec![
+                (1.0, vec![fixture("dir_1/file_1_3.png")]),
+                (1.0, vec![fixture("dir_1/file_1_1.sfx")]),
+                (
+                    1.0,
+                    vec![
+                        fixture("dir_1/subdir_1_2/some_file.txt"),
+                        fixture("dir_2/file_2_1.txt")
+                    ]
+                ),
+            ],
+            stratify(Balance::Ext, &sources)
+        );
+
+        assert_eq!(1, stratify(Balance::Files, &sources).len());
+
+        // Files outside every source are put together.
+        assert_eq!(
+            vec![
+                (1.0, vec![fixture("dir_2/file_2_1.txt")]),
+                (
+                    1.0,
+                    vec![
+                        fixture("dir_1/file_1_1.sfx"),
+                        fixture("dir_1/file_1_3.png"),
+                        fixture("dir_1/subdir_1_2/some_file.txt"),
+                    ]
+                ),
+            ],
+            stratify(Balance::Sources, &[(fixture("dir_2"), 1.0)])
+        );
+    }
+
+    #[test]
+    fn test_reservoir() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut stratifier = Stratifier::new(Balance::Files, &[], 30, Some(10));
+        let mut counts = [0; 100];
+
+        for _ in 0..1000 {
+            for file in files("/a", 100) {
+                stratifier.offer(file, &mut rng);
+            }
+
+            assert!(stratifier.is_truncated());
+
+            for (_weight, picks) in std::mem::replace(
+                &mut stratifier,
+                Stratifier::new(Balance::Files, &[], 30, Some(10)),
+            )
+            .into_strata()
+            {
+                assert_eq!(10, picks.len());
+
+                for pick in picks {
+                    counts[pick.file_stem().unwrap().parse::<usize>().unwrap()] += 1;
+                }
+            }
+        }
+
+        // Each file should be picked about 100 times in 1000 goes.
+        assert!(counts.iter().all(|&n| n > 50 && n < 150));
+    }
+}
@@ -0,0 +1,184 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::fs;
+use std::io::{self, Write};
+use tempfile::NamedTempFile;
+
+// randos sync keeps a destination directory holding a set number of random
+// files. It lists the files it made in a manifest in that directory, so next
+// time it knows which ones it may remove, and never touches anything else.
+
+pub const MANIFEST_NAME: &str = ".randos-sync";
+
+pub struct Manifest {
+    path: Utf8PathBuf,
+    // The name of each file we made in the destination, and its source.
+    pub entries: Vec<(String, Utf8PathBuf)>,
+}
+
+impl Manifest {
+    // Entries whose files have gone since the last run are dropped.
+    pub fn load(dest_dir: &Utf8Path) -> io::Result<Manifest> {
+        let path = dest_dir.join(MANIFEST_NAME);
+
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .lines()
+                .filter_map(|line| line.split_once('\t'))
+                .map(|(name, source)| (name.to_string(), Utf8PathBuf::from(source)))
+                .filter(|(name, _source)| dest_dir.join(name).symlink_metadata().is_ok())
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Manifest { path, entries })
+    }
+
+    // Decides which of the last run's files to keep, and returns the rest.
+    // Never more than count are kept, and churn (from 0 to 1) of those are
+    // replaced.
+    pub fn churn<R: Rng + ?Sized>(
+        &mut self,
+        count: usize,
+        churn: f64,
+        rng: &mut R,
+    ) -> Vec<(String, Utf8PathBuf)> {
+        self.entries.shuffle(rng);
+
+        let keep = self.entries.len().min(count);
+        let keep = keep - (keep as f64 * churn).round() as usize;
+        let ret = self.entries.split_off(keep);
+
+        self.entries.sort();
+        ret
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let dir = self.path.parent().unwrap_or(Utf8Path::new("."));
+        let mut tmp = NamedTempFile::new_in(dir)?;
+
+        for (name, source) in self.entries.iter() {
+            writeln!(tmp, "{}\t{}", name, source)?;
+        }
+
+        tmp.persist(&self.path)?;
+        Ok(())
+    }
+}
+
+// Removes a file we made, but not if something else has replaced it with a
+// directory.
+pub fn remove(file: &Utf8Path) -> io::Result<()> {
+    match file.symlink_metadata() {
+        Ok(metadata) if metadata.is_dir() => Err(io::Error::other(format!(
+            "{} is now a directory: not removing it",
+            file
+        ))),
+        Ok(_) => fs::remove_file(file),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+// With --preserve-tree, removing a file can leave its directories empty. Those
+// go too, up to, but not including, dest_dir.
+pub fn remove_empty_dirs(file: &Utf8Path, dest_dir: &Utf8Path) {
+    for dir in file.ancestors().skip(1) {
+        if dir == dest_dir || !dir.starts_with(dest_dir) || fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use camino_tempfile_ext::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn dest_dir(names: &[&str]) -> Utf8TempDir {
+        let dir = Utf8TempDir::new().unwrap();
+        let mut manifest = String::new();
+
+        for name in names {
+            dir.child(name).write_str(name).unwrap();
+            manifest.push_str(&format!("{}\t/src/{}\n", name, name));
+        }
+
+        manifest.push_str("gone.jpg\t/src/gone.jpg\n");
+        dir.child(MANIFEST_NAME).write_str(&manifest).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_load_and_save() {
+        let dir = dest_dir(&["a.jpg", "b.jpg"]);
+        let manifest = Manifest::load(dir.path()).unwrap();
+
+        assert_eq!(
+            vec![
+                ("a.jpg".to_string(), Utf8PathBuf::from("/src/a.jpg")),
+                ("b.jpg".to_string(), Utf8PathBuf::from("/src/b.jpg")),
+            ],
+            manifest.entries
+        );
+
+        manifest.save().unwrap();
+
+        assert_eq!(
+            "a.jpg\t/src/a.jpg\nb.jpg\t/src/b.jpg\n",
+            fs::read_to_string(dir.path().join(MANIFEST_NAME)).unwrap()
+        );
+
+        let empty = Utf8TempDir::new().unwrap();
+        assert!(Manifest::load(empty.path()).unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn test_churn() {
+        let dir = dest_dir(&["a", "b", "c", "d", "e", "f", "g", "h"]);
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let mut manifest = Manifest::load(dir.path()).unwrap();
+        assert_eq!(2, manifest.churn(8, 0.25, &mut rng).len());
+        assert_eq!(6, manifest.entries.len());
+
+        let mut manifest = Manifest::load(dir.path()).unwrap();
+        assert_eq!(4, manifest.churn(4, 0.0, &mut rng).len());
+        assert_eq!(4, manifest.entries.len());
+
+        let mut manifest = Manifest::load(dir.path()).unwrap();
+        assert_eq!(8, manifest.churn(10, 1.0, &mut rng).len());
+        assert!(manifest.entries.is_empty());
+    }
+
+    #[test]
+    fn test_remove() {
+        let dir = Utf8TempDir::new().unwrap();
+        dir.child("file").write_str("file").unwrap();
+        dir.child("dir").create_dir_all().unwrap();
+
+        assert!(remove(&dir.path().join("file")).is_ok());
+        assert!(!dir.path().join("file").exists());
+        assert!(remove(&dir.path().join("file")).is_ok());
+        assert!(remove(&dir.path().join("dir")).is_err());
+    }
+
+    #[test]
+    fn test_remove_empty_dirs() {
+        let dir = Utf8TempDir::new().unwrap();
+        dir.child("a/b/c/file").write_str("file").unwrap();
+        dir.child("a/other").write_str("other").unwrap();
+
+        let file = dir.path().join("a/b/c/file");
+        remove(&file).unwrap();
+        remove_empty_dirs(&file, dir.path());
+
+        assert!(!dir.path().join("a/b").exists());
+        assert!(dir.path().join("a/other").exists());
+        assert!(dir.path().exists());
+    }
+}
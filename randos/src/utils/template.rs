@@ -0,0 +1,310 @@
+use crate::utils::content::{self, Algorithm};
+use crate::utils::namer::Namer;
+use anyhow::{anyhow, bail};
+use camino::Utf8Path;
+use rand::distr::Alphanumeric;
+use rand::{Rng, RngExt};
+use std::fs;
+use std::time::UNIX_EPOCH;
+use time::OffsetDateTime;
+
+// --template names picks from a pattern, like {mtime:%Y%m%d}-{seq:05}-{name}.
+// Placeholders are in braces, with an optional argument after a colon, and
+// {{ and }} are literal braces. Times are UTC.
+
+#[derive(Debug, PartialEq)]
+enum Part {
+    Text(String),
+    // Zero-padded to a width
+    Seq(usize),
+    Name,
+    Stem,
+    Ext,
+    Parent,
+    // A strftime-like format
+    Mtime(String),
+    // The first so many hex digits of the content's digest
+    Hash(usize),
+    // So many random letters and digits
+    Rand(usize),
+    Size,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+    // How {hash} digests file contents.
+    algorithm: Algorithm,
+}
+
+impl Template {
+    pub fn parse(template: &str, algorithm: Algorithm) -> anyhow::Result<Template> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let Some(end) = rest.find('}') else {
+                        bail!("unclosed {{ in template '{}'", template);
+                    };
+
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+
+                    parts.push(placeholder(&rest[..end])?);
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => bail!("unmatched }} in template '{}'", template),
+                '/' => bail!("template '{}' can't contain /", template),
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        if parts.is_empty() {
+            bail!("template is empty");
+        }
+
+        Ok(Template { parts, algorithm })
+    }
+}
+
+fn placeholder(spec: &str) -> anyhow::Result<Part> {
+    let (name, arg) = match spec.split_once(':') {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (spec, None),
+    };
+
+    let number = |default: usize| -> anyhow::Result<usize> {
+        match arg {
+            Some(arg) => arg
+                .parse()
+                .map_err(|_| anyhow!("'{}' in {{{}}} is not a number", arg, spec)),
+            None => Ok(default),
+        }
+    };
+
+    let part = match name {
+        "seq" => Part::Seq(number(0)?),
+        "hash" => Part::Hash(number(usize::MAX)?),
+        "rand" => Part::Rand(number(32)?),
+        "mtime" if arg.is_some_and(|arg| arg.contains('/')) => {
+            bail!("{{{}}} can't contain /", spec)
+        }
+        "mtime" => Part::Mtime(arg.unwrap_or("%Y%m%d").to_string()),
+        _ if arg.is_some() => bail!("{{{}}} doesn't take an argument", name),
+        "name" => Part::Name,
+        "stem" => Part::Stem,
+        "ext" => Part::Ext,
+        "parent" => Part::Parent,
+        "size" => Part::Size,
+        _ => bail!("unknown placeholder {{{}}}", name),
+    };
+
+    Ok(part)
+}
+
+// The few strftime fields which make sense in a file name.
+fn format_time(time: OffsetDateTime, format: &str) -> String {
+    let mut ret = String::new();
+    let mut chars = format.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            ret.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('Y') => ret.push_str(&format!("{:04}", time.year())),
+            Some('y') => ret.push_str(&format!("{:02}", time.year() % 100)),
+            Some('m') => ret.push_str(&format!("{:02}", time.month() as u8)),
+            Some('d') => ret.push_str(&format!("{:02}", time.day())),
+            Some('j') => ret.push_str(&format!("{:03}", time.ordinal())),
+            Some('H') => ret.push_str(&format!("{:02}", time.hour())),
+            Some('M') => ret.push_str(&format!("{:02}", time.minute())),
+            Some('S') => ret.push_str(&format!("{:02}", time.second())),
+            Some('s') => ret.push_str(&time.unix_timestamp().to_string()),
+            Some(other) => {
+                ret.push('%');
+                ret.push(other);
+            }
+            None => ret.push('%'),
+        }
+    }
+
+    ret
+}
+
+impl Namer for Template {
+    // Files which can't be read, when the template needs to, are skipped, and
+    // so are any which it can't make a usable name for, like {ext} of a file
+    // with no extension. Where {ext} is empty the dot before it goes too, so
+    // {stem}.{ext} of README is README.
+    fn name(&self, path: &Utf8Path, seq_no: usize, rng: &mut dyn Rng) -> Option<String> {
+        let mut ret = String::new();
+
+        for part in self.parts.iter() {
+            match part {
+                Part::Text(text) => ret.push_str(text),
+                Part::Seq(width) => ret.push_str(&format!("{:0width$}", seq_no, width = *width)),
+                Part::Name => ret.push_str(path.file_name()?),
+                Part::Stem => ret.push_str(path.file_stem().unwrap_or_default()),
+                Part::Ext => match path.extension() {
+                    Some(ext) if !ext.is_empty() => ret.push_str(ext),
+                    _ => {
+                        if ret.ends_with('.') {
+                            ret.pop();
+                        }
+                    }
+                },
+                Part::Parent => ret.push_str(
+                    path.parent()
+                        .and_then(|parent| parent.file_name())
+                        .unwrap_or_default(),
+                ),
+                Part::Mtime(format) => {
+                    let mtime = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+                    let secs = mtime.duration_since(UNIX_EPOCH).ok()?.as_secs();
+                    let time = OffsetDateTime::from_unix_timestamp(secs as i64).ok()?;
+                    ret.push_str(&format_time(time, format));
+                }
+                Part::Hash(len) => {
+                    let digest = content::digest(path, self.algorithm).ok()?;
+                    ret.push_str(&digest[..digest.len().min(*len)]);
+                }
+                Part::Rand(len) => ret.extend(
+                    (&mut *rng)
+                        .sample_iter(&Alphanumeric)
+                        .take(*len)
+                        .map(char::from),
+                ),
+                Part::Size => ret.push_str(&fs::metadata(path).ok()?.len().to_string()),
+            }
+        }
+
+        Some(ret).filter(|name| !matches!(name.as_str(), "" | "." | "..") && !name.contains('/'))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use camino_tempfile_ext::prelude::*;
+    use filetime::FileTime;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use test_utils::fixture;
+
+    fn name(template: &str, path: &Utf8Path, seq_no: usize) -> Option<String> {
+        let mut rng = StdRng::seed_from_u64(1);
+        Template::parse(template, Algorithm::Sha1)
+            .unwrap()
+            .name(path, seq_no, &mut rng)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Template {
+                parts: vec![
+                    Part::Seq(5),
+                    Part::Text("-{".to_string()),
+                    Part::Stem,
+                    Part::Text("}.".to_string()),
+                    Part::Ext,
+                ],
+                algorithm: Algorithm::Sha1,
+            },
+            Template::parse("{seq:05}-{{{stem}}}.{ext}", Algorithm::Sha1).unwrap()
+        );
+
+        assert!(Template::parse("{nope}", Algorithm::Sha1).is_err());
+        assert!(Template::parse("{seq:five}", Algorithm::Sha1).is_err());
+        assert!(Template::parse("{stem:3}", Algorithm::Sha1).is_err());
+        assert!(Template::parse("{stem", Algorithm::Sha1).is_err());
+        assert!(Template::parse("stem}", Algorithm::Sha1).is_err());
+        assert!(Template::parse("{parent}/{name}", Algorithm::Sha1).is_err());
+        assert!(Template::parse("{mtime:%Y/%m}-{name}", Algorithm::Sha1).is_err());
+        assert!(Template::parse("", Algorithm::Sha1).is_err());
+    }
+
+    #[test]
+    fn test_name() {
+        let file = fixture("dir_1/file_1_3.png");
+
+        assert_eq!(
+            Some("00042-file_1_3.png".to_string()),
+            name("{seq:05}-{stem}.{ext}", &file, 42)
+        );
+        assert_eq!(
+            Some("dir_1-file_1_3.png-13".to_string()),
+            name("{parent}-{name}-{size}", &file, 0)
+        );
+        assert_eq!(
+            Some("6537488a.png".to_string()),
+            name("{hash:8}.{ext}", &file, 0)
+        );
+        assert_eq!(
+            Some("6537488a4569d7ea329b8bb44cd0dd0698889ccc".to_string()),
+            name("{hash:99}", &file, 0)
+        );
+        assert_eq!(
+            32,
+            Template::parse("{hash}", Algorithm::Xxh3)
+                .unwrap()
+                .name(&file, 0, &mut StdRng::seed_from_u64(1))
+                .unwrap()
+                .len()
+        );
+        assert_eq!(6, name("{rand:6}", &file, 0).unwrap().len());
+        assert_eq!(None, name("{size}", &fixture("dir_1/no_such_file"), 0));
+    }
+
+    #[test]
+    fn test_unusable_name() {
+        let dir = Utf8TempDir::new().unwrap();
+        let file = dir.path().join("README");
+        fs::write(&file, "readme").unwrap();
+
+        assert_eq!(None, name("{ext}", &file, 0));
+        assert_eq!(Some("README".to_string()), name("{stem}.{ext}", &file, 0));
+        assert_eq!(None, name(".{ext}", &file, 0));
+        assert_eq!(None, name("..{ext}", &file, 0));
+    }
+
+    #[test]
+    fn test_mtime() {
+        let dir = Utf8TempDir::new().unwrap();
+        let file = dir.path().join("cover.jpg");
+        fs::write(&file, "cover").unwrap();
+
+        // 2024-01-01 12:34:56 UTC
+        filetime::set_file_mtime(&file, FileTime::from_unix_time(1_704_112_496, 0)).unwrap();
+
+        assert_eq!(
+            Some("20240101-000123-cover.jpg".to_string()),
+            name("{mtime:%Y%m%d}-{seq:06}-{name}", &file, 123)
+        );
+        assert_eq!(
+            Some("24-001 12h34m56s %q".to_string()),
+            name("{mtime:%y-%j %Hh%Mm%Ss %q}", &file, 0)
+        );
+        assert_eq!(Some("20240101".to_string()), name("{mtime}", &file, 0));
+    }
+}
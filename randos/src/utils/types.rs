@@ -1,15 +1,26 @@
+use crate::utils::actions::{Action, Verify};
+use crate::utils::filter::FileType;
 use regex::Regex;
 
+#[derive(Default)]
 pub struct FilterOpts {
     pub extensions: Option<Vec<String>>,
     pub newer: Option<u64>,
     pub older: Option<u64>,
     pub regex: Option<Regex>,
+    pub exclude: Vec<Regex>,
+    pub exclude_globs: Vec<glob::Pattern>,
+    pub full_path: bool,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub types: Option<Vec<FileType>>,
 }
 
 pub struct ActionOpts {
-    pub action: String,
+    pub action: Action,
+    pub auto_reflink: bool,
     pub noop: bool,
     pub relative_links: bool,
     pub verbose: bool,
+    pub verify: Verify,
 }
@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+
+    #[test]
+    #[ignore]
+    fn test_randos_rejects_unknown_action() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+        src_dir.child("file.jpg").write_str("file").unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("cpy")
+            .arg("-r")
+            .arg("1")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .failure()
+            .stderr(predicates::str::contains("invalid value 'cpy'"));
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("-r")
+            .arg("-s")
+            .arg("plian")
+            .arg("1")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .failure()
+            .stderr(predicates::str::contains("invalid value 'plian'"));
+
+        assert_eq!(0, target_dir.path().read_dir_utf8().unwrap().count());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_aliases() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+        src_dir.child("file.jpg").write_str("file").unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("copy")
+            .arg("-r")
+            .arg("-s")
+            .arg("seq")
+            .arg("1")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        let copy = target_dir.path().join("00000000.jpg");
+        assert!(!copy.symlink_metadata().unwrap().is_symlink());
+    }
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+
+    fn source_dir(prefix: &str, count: usize) -> Utf8TempDir {
+        let dir = Utf8TempDir::new().unwrap();
+
+        for i in 0..count {
+            dir.child(format!("{}_{}.sfx", prefix, i))
+                .write_str(prefix)
+                .unwrap();
+        }
+
+        dir
+    }
+
+    fn count_from(target_dir: &Utf8TempDir, prefix: &str) -> usize {
+        target_dir
+            .path()
+            .read_dir_utf8()
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().file_name().starts_with(prefix))
+            .count()
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_balance_sources() {
+        let big = source_dir("big", 100);
+        let small = source_dir("small", 5);
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("-r")
+            .arg("--balance")
+            .arg("sources")
+            .arg("10")
+            .arg(big.path())
+            .arg(small.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        assert_eq!(5, count_from(&target_dir, "big"));
+        assert_eq!(5, count_from(&target_dir, "small"));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_weighted_sources() {
+        let first = source_dir("first", 20);
+        let second = source_dir("second", 20);
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("-r")
+            .arg("8")
+            .arg(format!("{}:3", first.path()))
+            .arg(second.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        assert_eq!(6, count_from(&target_dir, "first"));
+        assert_eq!(2, count_from(&target_dir, "second"));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_bad_weight() {
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("1")
+            .arg("/no/such/dir:-1")
+            .arg(target_dir.path())
+            .assert()
+            .failure();
+    }
+}
@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+
+    fn used(target_dir: &Utf8TempDir) -> (usize, u64) {
+        target_dir
+            .path()
+            .read_dir_utf8()
+            .unwrap()
+            .map(|entry| entry.unwrap().metadata().unwrap().len())
+            .fold((0, 0), |(files, bytes), size| (files + 1, bytes + size))
+    }
+
+    fn source_dir() -> Utf8TempDir {
+        let src_dir = Utf8TempDir::new().unwrap();
+
+        for i in 0..10 {
+            src_dir
+                .child(format!("big_{}.sfx", i))
+                .write_str(&"x".repeat(300))
+                .unwrap();
+            src_dir
+                .child(format!("small_{}.sfx", i))
+                .write_str(&"x".repeat(10))
+                .unwrap();
+        }
+
+        src_dir
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_budget() {
+        let src_dir = source_dir();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("-r")
+            .arg("--budget")
+            .arg("1K")
+            .arg("0")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        let (files, bytes) = used(&target_dir);
+        assert!(files > 0);
+        assert!(bytes <= 1024);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_budget_best_fit() {
+        let src_dir = source_dir();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("-r")
+            .arg("--budget")
+            .arg("1000")
+            .arg("--best-fit")
+            .arg("0")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        // Whatever the random picks were, best fit leaves less room than the
+        // smallest file.
+        let (_files, bytes) = used(&target_dir);
+        assert!(bytes <= 1000);
+        assert!(bytes > 990);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_budget_count() {
+        let src_dir = source_dir();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("-r")
+            .arg("--budget")
+            .arg("1G")
+            .arg("3")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        assert_eq!(3, used(&target_dir).0);
+    }
+}
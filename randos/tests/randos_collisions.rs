@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+    use std::collections::BTreeSet;
+    use std::fs;
+
+    fn setup() -> (Utf8TempDir, Utf8TempDir) {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        for dir in ["a", "b", "c"] {
+            src_dir
+                .child(format!("{}/cover.jpg", dir))
+                .write_str(dir)
+                .unwrap();
+        }
+
+        target_dir.child("cover.jpg").write_str("mine").unwrap();
+        (src_dir, target_dir)
+    }
+
+    fn names(dir: &Utf8TempDir) -> BTreeSet<String> {
+        dir.path()
+            .read_dir_utf8()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string())
+            .collect()
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_collisions_suffix() {
+        let (src_dir, target_dir) = setup();
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("-r")
+            .arg("3")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        assert_eq!(
+            BTreeSet::from([
+                "cover.jpg".to_string(),
+                "cover-1.jpg".to_string(),
+                "cover-2.jpg".to_string(),
+                "cover-3.jpg".to_string(),
+            ]),
+            names(&target_dir)
+        );
+
+        assert_eq!(
+            "mine",
+            fs::read_to_string(target_dir.path().join("cover.jpg")).unwrap()
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_collisions_skip() {
+        let (src_dir, target_dir) = setup();
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("-r")
+            .arg("--collisions")
+            .arg("skip")
+            .arg("3")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success()
+            .stdout(predicates::str::contains("but 0 suitable candidates"));
+
+        assert_eq!(
+            "mine",
+            fs::read_to_string(target_dir.path().join("cover.jpg")).unwrap()
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_collisions_expand() {
+        let (src_dir, target_dir) = setup();
+
+        cargo_bin_cmd!("randos")
+            .arg("ln")
+            .arg("-r")
+            .arg("--collisions")
+            .arg("expand")
+            .arg("1")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        let names = names(&target_dir);
+        assert_eq!(2, names.len());
+        assert!(names.iter().any(|name| name.ends_with("-cover.jpg")));
+    }
+}
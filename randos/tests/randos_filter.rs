@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    #[ignore]
+    fn test_randos_filters() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        src_dir
+            .child("top.jpg")
+            .write_str(&"x".repeat(100))
+            .unwrap();
+        src_dir.child("tiny.jpg").write_str("x").unwrap();
+        src_dir
+            .child("skip.tmp")
+            .write_str(&"x".repeat(100))
+            .unwrap();
+        src_dir
+            .child("a/mid.jpg")
+            .write_str(&"x".repeat(100))
+            .unwrap();
+        src_dir
+            .child("a/.git/obj.jpg")
+            .write_str(&"x".repeat(100))
+            .unwrap();
+        src_dir
+            .child("a/b/deep.jpg")
+            .write_str(&"x".repeat(100))
+            .unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("ln")
+            .arg("--max-depth")
+            .arg("2")
+            .arg("--min-size")
+            .arg("10")
+            .arg("--exclude-glob")
+            .arg("*.tmp")
+            .arg("--exclude-glob")
+            .arg("*/.git/*")
+            .arg("-N")
+            .arg("1h")
+            .arg("10")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        let names: BTreeSet<String> = target_dir
+            .path()
+            .read_dir_utf8()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string())
+            .collect();
+
+        assert_eq!(
+            BTreeSet::from(["mid.jpg".to_string(), "top.jpg".to_string()]),
+            names
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_bad_duration() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("ln")
+            .arg("-O")
+            .arg("3 fortnights")
+            .arg("1")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .failure()
+            .stderr(predicates::str::contains("is not a duration"));
+    }
+}
@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+    use std::collections::BTreeSet;
+
+    fn names(dir: &Utf8TempDir) -> BTreeSet<String> {
+        dir.path()
+            .read_dir_utf8()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string())
+            .collect()
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_from_stdin() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        for name in ["a.jpg", "b.jpg", "c.txt", "with\nnewline.jpg"] {
+            src_dir.child(name).write_str(name).unwrap();
+        }
+
+        let list: String = ["a.jpg", "c.txt", "with\nnewline.jpg", "missing.jpg"]
+            .iter()
+            .map(|name| format!("{}\0", src_dir.path().join(name)))
+            .collect();
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("-e")
+            .arg("jpg")
+            .arg("--from")
+            .arg("-")
+            .arg("-0")
+            .arg("10")
+            .arg(target_dir.path())
+            .write_stdin(list)
+            .assert()
+            .success()
+            .stderr(predicates::str::contains("missing.jpg does not exist"));
+
+        assert_eq!(
+            BTreeSet::from(["a.jpg".to_string(), "with\nnewline.jpg".to_string()]),
+            names(&target_dir)
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_from_file() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let list_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        src_dir.child("listed/a.jpg").write_str("a").unwrap();
+        src_dir.child("given/b.jpg").write_str("b").unwrap();
+        src_dir.child("other/c.jpg").write_str("c").unwrap();
+
+        list_dir
+            .child("list.txt")
+            .write_str(&format!("{}\n", src_dir.path().join("listed")))
+            .unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("ln")
+            .arg("-r")
+            .arg("--from")
+            .arg(list_dir.path().join("list.txt"))
+            .arg("10")
+            .arg(src_dir.path().join("given"))
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        assert_eq!(
+            BTreeSet::from(["a.jpg".to_string(), "b.jpg".to_string()]),
+            names(&target_dir)
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_no_sources() {
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("ln")
+            .arg("10")
+            .arg(target_dir.path())
+            .assert()
+            .failure()
+            .stderr(predicates::str::contains("no sources given"));
+    }
+}
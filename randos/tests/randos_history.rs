@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino::Utf8Path;
+    use camino_tempfile_ext::prelude::*;
+    use std::collections::BTreeSet;
+    use std::fs;
+
+    fn picks(src_dir: &Utf8TempDir, history: &Utf8Path, count: usize) -> BTreeSet<String> {
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("-r")
+            .arg("--history")
+            .arg(history)
+            .arg("--no-repeat")
+            .arg(count.to_string())
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        target_dir
+            .path()
+            .read_dir_utf8()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string())
+            .collect()
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_no_repeat() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let state_dir = Utf8TempDir::new().unwrap();
+        let history = state_dir.path().join("history");
+
+        for i in 0..5 {
+            src_dir
+                .child(format!("file_{}.sfx", i))
+                .write_str("content")
+                .unwrap();
+        }
+
+        let first = picks(&src_dir, &history, 2);
+        let second = picks(&src_dir, &history, 2);
+
+        assert_eq!(2, first.len());
+        assert_eq!(2, second.len());
+        assert!(first.is_disjoint(&second));
+        assert_eq!(4, fs::read_to_string(&history).unwrap().lines().count());
+
+        // Only one file hasn't been picked, so the run starts a new cycle for
+        // its second pick. The new cycle's history holds both of this run's
+        // picks, so neither turns up again straight away.
+        let third = picks(&src_dir, &history, 2);
+        let recorded = fs::read_to_string(&history).unwrap();
+
+        assert_eq!(2, third.len());
+        assert_eq!(
+            1,
+            third
+                .iter()
+                .filter(|f| !first.contains(*f) && !second.contains(*f))
+                .count()
+        );
+        assert_eq!(2, recorded.lines().count());
+        assert!(third.iter().all(|f| recorded.contains(f.as_str())));
+    }
+}
@@ -0,0 +1,160 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+    use filetime::FileTime;
+    use std::collections::BTreeSet;
+    use std::fs;
+
+    fn sources(count: usize) -> Utf8TempDir {
+        let src_dir = Utf8TempDir::new().unwrap();
+
+        for i in 0..count {
+            let file = src_dir.child(format!("file_{:02}.sfx", i));
+            file.write_str(&format!("{}", i).repeat(1000)).unwrap();
+            filetime::set_file_mtime(file.as_path(), FileTime::from_unix_time(1_600_000_000, 0))
+                .unwrap();
+        }
+
+        src_dir
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_jobs() {
+        let src_dir = sources(20);
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("-r")
+            .arg("-j")
+            .arg("4")
+            .arg("--progress")
+            .arg("20")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success()
+            .stderr(predicates::str::contains("20/20 files"));
+
+        for entry in target_dir.path().read_dir_utf8().unwrap() {
+            let entry = entry.unwrap();
+            let source = src_dir.path().join(entry.file_name());
+
+            assert_eq!(
+                fs::read_to_string(&source).unwrap(),
+                fs::read_to_string(entry.path()).unwrap()
+            );
+            assert_eq!(
+                FileTime::from_unix_time(1_600_000_000, 0),
+                FileTime::from_last_modification_time(&entry.metadata().unwrap())
+            );
+        }
+
+        assert_eq!(20, target_dir.path().read_dir_utf8().unwrap().count());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_mvx_verify() {
+        let src_dir = sources(5);
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("mvx")
+            .arg("-r")
+            .arg("-j")
+            .arg("2")
+            .arg("--verify")
+            .arg("hash")
+            .arg("5")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        assert_eq!(0, src_dir.path().read_dir_utf8().unwrap().count());
+        assert_eq!(5, target_dir.path().read_dir_utf8().unwrap().count());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_resume() {
+        let src_dir = sources(10);
+        let target_dir = Utf8TempDir::new().unwrap();
+        let record_dir = Utf8TempDir::new().unwrap();
+        let record = record_dir.path().join("run.csv");
+
+        let run = |count: &str| {
+            cargo_bin_cmd!("randos")
+                .arg("cp")
+                .arg("-r")
+                .arg("--record")
+                .arg(&record)
+                .arg("--resume")
+                .arg(count)
+                .arg(src_dir.path())
+                .arg(target_dir.path())
+                .assert()
+                .success();
+        };
+
+        // As if the first run had been stopped after three files, part-way
+        // through copying a file which the next run doesn't pick.
+        run("3");
+        let stale = target_dir.path().join(".gone.sfx.12345678.randos-part");
+        fs::write(&stale, "0").unwrap();
+        run("6");
+
+        let names: BTreeSet<String> = target_dir
+            .path()
+            .read_dir_utf8()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string())
+            .collect();
+
+        assert_eq!(6, names.len());
+        assert!(names.iter().all(|name| !name.contains('-')));
+        assert!(!stale.exists());
+        assert_eq!(7, fs::read_to_string(&record).unwrap().lines().count());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_resume_keeps_seed() {
+        let src_dir = sources(10);
+        let target_dir = Utf8TempDir::new().unwrap();
+        let record_dir = Utf8TempDir::new().unwrap();
+        let record = record_dir.path().join("run.csv");
+
+        let run = |count: &str| {
+            cargo_bin_cmd!("randos")
+                .arg("cp")
+                .arg("-r")
+                .arg("-v")
+                .arg("--record")
+                .arg(&record)
+                .arg("--resume")
+                .arg(count)
+                .arg(src_dir.path())
+                .arg(target_dir.path())
+                .assert()
+                .success()
+        };
+
+        run("2");
+
+        let seed = fs::read_to_string(&record)
+            .unwrap()
+            .lines()
+            .nth(1)
+            .unwrap()
+            .rsplit(',')
+            .next()
+            .unwrap()
+            .to_string();
+
+        run("4").stdout(predicates::str::contains(format!("seed: {}\n", seed)));
+    }
+}
@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+    use std::fs;
+
+    // Whether the files are cloned depends on the filesystem the tests run on,
+    // but either way they must end up as independent copies.
+    fn check_clones(action: &str, flags: &[&str]) {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        for i in 0..3 {
+            src_dir
+                .child(format!("file_{}.sfx", i))
+                .write_str(&format!("content {}", i))
+                .unwrap();
+        }
+
+        cargo_bin_cmd!("randos")
+            .arg(action)
+            .args(flags)
+            .arg("-r")
+            .arg("3")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        for i in 0..3 {
+            let name = format!("file_{}.sfx", i);
+            let dest = target_dir.path().join(&name);
+
+            assert!(!dest.symlink_metadata().unwrap().is_symlink());
+            assert_eq!(format!("content {}", i), fs::read_to_string(&dest).unwrap());
+
+            fs::write(&dest, "changed").unwrap();
+            assert_eq!(
+                format!("content {}", i),
+                fs::read_to_string(src_dir.path().join(&name)).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_reflink() {
+        check_clones("reflink", &[]);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_cp_auto_reflink() {
+        check_clones("cp", &["--auto-reflink"]);
+    }
+}
@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+    use std::fs;
+
+    fn picks(src_dir: &Utf8TempDir, seed: &str) -> Vec<(String, String)> {
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("-r")
+            .arg("--seed")
+            .arg(seed)
+            .arg("-s")
+            .arg("random")
+            .arg("5")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        let mut ret: Vec<(String, String)> = target_dir
+            .path()
+            .read_dir_utf8()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let contents = fs::read_to_string(entry.path()).unwrap();
+                (entry.file_name().to_string(), contents)
+            })
+            .collect();
+
+        ret.sort();
+        ret
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_seed() {
+        let src_dir = Utf8TempDir::new().unwrap();
+
+        for i in 0..20 {
+            src_dir
+                .child(format!("file_{}.sfx", i))
+                .write_str(&format!("file {}", i))
+                .unwrap();
+        }
+
+        let first = picks(&src_dir, "42");
+
+        assert_eq!(5, first.len());
+        assert_eq!(first, picks(&src_dir, "42"));
+        assert_ne!(first, picks(&src_dir, "43"));
+    }
+}
@@ -0,0 +1,144 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+    use std::collections::BTreeSet;
+    use std::fs;
+
+    fn sync(src_dir: &Utf8TempDir, target_dir: &Utf8TempDir, churn: &str) {
+        cargo_bin_cmd!("randos")
+            .arg("sync")
+            .arg("-r")
+            .arg("--via")
+            .arg("cp")
+            .arg("--churn")
+            .arg(churn)
+            .arg("4")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+    }
+
+    fn contents(target_dir: &Utf8TempDir) -> BTreeSet<String> {
+        target_dir
+            .path()
+            .read_dir_utf8()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string())
+            .filter(|name| name != ".randos-sync")
+            .collect()
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_sync() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        for i in 0..20 {
+            src_dir
+                .child(format!("file_{:02}.sfx", i))
+                .write_str("content")
+                .unwrap();
+        }
+
+        target_dir.child("mine.txt").write_str("mine").unwrap();
+        target_dir
+            .child("file_00.sfx")
+            .write_str("mine too")
+            .unwrap();
+
+        sync(&src_dir, &target_dir, "100%");
+        let first = contents(&target_dir);
+
+        assert_eq!(6, first.len());
+        assert_eq!(
+            "mine too",
+            fs::read_to_string(target_dir.path().join("file_00.sfx")).unwrap()
+        );
+
+        sync(&src_dir, &target_dir, "25%");
+        let second = contents(&target_dir);
+
+        assert_eq!(6, second.len());
+        assert!(second.contains("mine.txt"));
+        assert!(first.intersection(&second).count() >= 5);
+
+        sync(&src_dir, &target_dir, "0");
+        assert_eq!(second, contents(&target_dir));
+
+        // Files we made, which have been removed by hand, are replaced.
+        let gone = second
+            .iter()
+            .find(|name| !name.starts_with("mine") && *name != "file_00.sfx")
+            .unwrap();
+
+        fs::remove_file(target_dir.path().join(gone)).unwrap();
+        sync(&src_dir, &target_dir, "0");
+        assert_eq!(6, contents(&target_dir).len());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_sync_keeps_what_it_cannot_remove() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        for i in 0..20 {
+            src_dir
+                .child(format!("file_{:02}.sfx", i))
+                .write_str("content")
+                .unwrap();
+        }
+
+        sync(&src_dir, &target_dir, "0");
+
+        // Something else has put a directory where one of our files was.
+        let stuck = contents(&target_dir).into_iter().next().unwrap();
+        let stuck_path = target_dir.path().join(&stuck);
+        fs::remove_file(&stuck_path).unwrap();
+        fs::create_dir(&stuck_path).unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("sync")
+            .arg("-r")
+            .arg("--via")
+            .arg("cp")
+            .arg("--churn")
+            .arg("100%")
+            .arg("4")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .failure();
+
+        assert!(stuck_path.is_dir());
+        assert_eq!(4, contents(&target_dir).len());
+
+        let manifest = fs::read_to_string(target_dir.path().join(".randos-sync")).unwrap();
+        assert_eq!(4, manifest.lines().count());
+        assert!(
+            manifest
+                .lines()
+                .any(|line| line.starts_with(&format!("{}\t", stuck)))
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_sync_refuses_to_move() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("sync")
+            .arg("--via")
+            .arg("mv")
+            .arg("4")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .failure();
+    }
+}
@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+    use filetime::FileTime;
+    use std::collections::BTreeSet;
+
+    #[test]
+    #[ignore]
+    fn test_randos_template() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        for dir in ["a", "b"] {
+            let file = src_dir.child(format!("{}/cover.jpg", dir));
+            file.write_str(dir).unwrap();
+
+            // 2024-01-01 UTC
+            filetime::set_file_mtime(file.as_path(), FileTime::from_unix_time(1_704_112_496, 0))
+                .unwrap();
+        }
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("-r")
+            .arg("--template")
+            .arg("{mtime:%Y%m%d}-{parent}-{stem}.{ext}")
+            .arg("2")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        let names: BTreeSet<String> = target_dir
+            .path()
+            .read_dir_utf8()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string())
+            .collect();
+
+        assert_eq!(
+            BTreeSet::from([
+                "20240101-a-cover.jpg".to_string(),
+                "20240101-b-cover.jpg".to_string()
+            ]),
+            names
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_bad_template() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("--template")
+            .arg("{seq:05}-{title}")
+            .arg("1")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .failure()
+            .stderr(predicates::str::contains("unknown placeholder {title}"));
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("--template")
+            .arg("{mtime:%Y/%m}-{name}")
+            .arg("1")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .failure()
+            .stderr(predicates::str::contains("{mtime:%Y/%m} can't contain /"));
+    }
+}
@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+    use std::fs;
+
+    #[test]
+    #[ignore]
+    fn test_randos_preserve_tree() {
+        for action in ["ln", "lnh", "cp", "mv", "mvx"] {
+            let src_dir = Utf8TempDir::new().unwrap();
+            let target_dir = Utf8TempDir::new().unwrap();
+
+            src_dir.child("top.jpg").write_str("top").unwrap();
+            src_dir.child("a/b/deep.jpg").write_str("deep").unwrap();
+
+            cargo_bin_cmd!("randos")
+                .arg(action)
+                .arg("-r")
+                .arg("-R")
+                .arg("--preserve-tree")
+                .arg("2")
+                .arg(src_dir.path())
+                .arg(target_dir.path())
+                .assert()
+                .success();
+
+            assert_eq!(
+                "top",
+                fs::read_to_string(target_dir.path().join("top.jpg")).unwrap(),
+                "{}",
+                action
+            );
+            assert_eq!(
+                "deep",
+                fs::read_to_string(target_dir.path().join("a/b/deep.jpg")).unwrap(),
+                "{}",
+                action
+            );
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_sync_preserve_tree() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        for i in 0..6 {
+            src_dir
+                .child(format!("dir_{}/sub/file.jpg", i))
+                .write_str("file")
+                .unwrap();
+        }
+
+        let sync = || {
+            cargo_bin_cmd!("randos")
+                .arg("sync")
+                .arg("-r")
+                .arg("--preserve-tree")
+                .arg("2")
+                .arg(src_dir.path())
+                .arg(target_dir.path())
+                .assert()
+                .success();
+        };
+
+        sync();
+        sync();
+
+        // The manifest, and the two picks from the second run, with nothing
+        // left behind from the first.
+        assert_eq!(3, target_dir.path().read_dir_utf8().unwrap().count());
+
+        let manifest = fs::read_to_string(target_dir.path().join(".randos-sync")).unwrap();
+        assert_eq!(2, manifest.lines().count());
+        assert!(manifest.lines().all(|line| line.starts_with("dir_")));
+    }
+}
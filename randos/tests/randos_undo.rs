@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+    use std::fs;
+
+    #[test]
+    #[ignore]
+    fn test_randos_record_and_undo() {
+        for (action, record) in [("cp", "run.jsonl"), ("mv", "run.csv"), ("ln", "run.jsonl")] {
+            let src_dir = Utf8TempDir::new().unwrap();
+            let target_dir = Utf8TempDir::new().unwrap();
+            let record_dir = Utf8TempDir::new().unwrap();
+            let record = record_dir.path().join(record);
+
+            for i in 0..5 {
+                src_dir
+                    .child(format!("sub/file_{}.jpg", i))
+                    .write_str(&format!("file {}", i))
+                    .unwrap();
+            }
+
+            cargo_bin_cmd!("randos")
+                .arg(action)
+                .arg("-r")
+                .arg("--seed")
+                .arg("7")
+                .arg("--record")
+                .arg(&record)
+                .arg("3")
+                .arg(src_dir.path())
+                .arg(target_dir.path())
+                .assert()
+                .success();
+
+            let written = fs::read_to_string(&record).unwrap();
+            assert!(written.contains(&format!("{}", src_dir.path().join("sub"))));
+            assert!(written.contains("7"));
+            assert_eq!(3, target_dir.path().read_dir_utf8().unwrap().count());
+
+            cargo_bin_cmd!("randos")
+                .arg("undo")
+                .arg(&record)
+                .assert()
+                .success();
+
+            assert_eq!(0, target_dir.path().read_dir_utf8().unwrap().count());
+            assert_eq!(
+                5,
+                src_dir.path().join("sub").read_dir_utf8().unwrap().count(),
+                "{}",
+                action
+            );
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_randos_undo_after_flags() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+        let record_dir = Utf8TempDir::new().unwrap();
+        let record = record_dir.path().join("run.jsonl");
+
+        src_dir.child("sub/file.jpg").write_str("file").unwrap();
+
+        cargo_bin_cmd!("randos")
+            .arg("mv")
+            .arg("-r")
+            .arg("--record")
+            .arg(&record)
+            .arg("1")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success();
+
+        cargo_bin_cmd!("randos")
+            .arg("-n")
+            .arg("undo")
+            .arg(&record)
+            .assert()
+            .success();
+
+        assert!(!src_dir.path().join("sub/file.jpg").exists());
+
+        cargo_bin_cmd!("randos")
+            .arg("-v")
+            .arg("undo")
+            .arg(&record)
+            .assert()
+            .success()
+            .stdout(predicates::str::contains("file.jpg"));
+
+        assert!(src_dir.path().join("sub/file.jpg").exists());
+        assert_eq!(0, target_dir.path().read_dir_utf8().unwrap().count());
+    }
+}
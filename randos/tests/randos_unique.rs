@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod test {
+    use assert_cmd::cargo::cargo_bin_cmd;
+    use camino_tempfile_ext::prelude::*;
+    use std::collections::BTreeSet;
+    use std::fs;
+
+    #[test]
+    #[ignore]
+    fn test_randos_unique_content() {
+        let src_dir = Utf8TempDir::new().unwrap();
+        let target_dir = Utf8TempDir::new().unwrap();
+
+        for dir in ["a", "b", "c"] {
+            for i in 0..3 {
+                src_dir
+                    .child(format!("{}/file_{}.sfx", dir, i))
+                    .write_str(&format!("content {}", i))
+                    .unwrap();
+            }
+        }
+
+        cargo_bin_cmd!("randos")
+            .arg("cp")
+            .arg("-r")
+            .arg("--unique-content")
+            .arg("-s")
+            .arg("content-hash")
+            .arg("9")
+            .arg(src_dir.path())
+            .arg(target_dir.path())
+            .assert()
+            .success()
+            .stdout(predicates::str::contains("but 3 suitable candidates"));
+
+        let contents: BTreeSet<String> = target_dir
+            .path()
+            .read_dir_utf8()
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+
+        assert_eq!(3, contents.len());
+        assert_eq!(3, target_dir.path().read_dir_utf8().unwrap().count());
+    }
+}